@@ -0,0 +1,58 @@
+//! Notes an agent keeps between steps and runs
+
+use std::{collections::BTreeMap, sync::Mutex};
+
+/// A key-value scratchpad shown to the agent in its system prompt
+///
+/// The store is shared, so notes written by the agent's `remember` tool can be read by
+/// the caller and survive across runs of the same agent.
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::agent::Memory;
+///
+/// let memory = Memory::default();
+/// memory.set("user", "prefers metric units");
+/// assert_eq!(memory.get("user").as_deref(), Some("prefers metric units"));
+/// assert_eq!(memory.render().unwrap(), "- user: prefers metric units");
+/// ```
+#[derive(Debug, Default)]
+pub struct Memory {
+    entries: Mutex<BTreeMap<String, String>>,
+}
+
+impl Memory {
+    pub fn set(&self, key: impl Into<String>, value: impl Into<String>) {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.into(), value.into());
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    /// Removes a note, returning whether it existed
+    pub fn remove(&self, key: &str) -> bool {
+        self.entries.lock().unwrap().remove(key).is_some()
+    }
+
+    /// Returns a copy of all notes
+    pub fn entries(&self) -> BTreeMap<String, String> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    /// Renders the notes as a Markdown list, or `None` when there are none
+    pub fn render(&self) -> Option<String> {
+        let entries = self.entries.lock().unwrap();
+        if entries.is_empty() {
+            return None;
+        }
+        let lines: Vec<String> = entries
+            .iter()
+            .map(|(key, value)| format!("- {}: {}", key, value))
+            .collect();
+        Some(lines.join("\n"))
+    }
+}
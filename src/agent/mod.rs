@@ -0,0 +1,490 @@
+//! Tool-using agents built on chat completions
+//!
+//! An [`Agent`] sends a task to a [`ChatProvider`] together with its system prompt,
+//! tools and [`Memory`], runs the tools the model calls, and repeats until the model
+//! gives a final answer or a step or token limit is reached.
+
+pub mod memory;
+pub mod tools;
+
+use std::sync::Arc;
+
+use serde_json::Value;
+
+pub use memory::Memory;
+pub use tools::{ToolHandler, ToolRegistry};
+
+use crate::{
+    client::provider::ChatProvider,
+    errors::request_errors::RequestErrors,
+    types::{
+        request::{Message, RequestBody},
+        response::Usage,
+    },
+};
+
+/// Why an [`Agent`] run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The model gave a final answer
+    FinalAnswer,
+    /// The step limit was reached
+    MaxSteps,
+    /// The run used up its token budget
+    TokenBudget,
+}
+
+/// A tool call made during a step, with its result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolInvocation {
+    pub id: String,
+    pub name: String,
+    pub arguments: String,
+    pub output: Result<String, String>,
+}
+
+/// One model call of an agent run and the tool calls it requested
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentStep {
+    /// Counts from 1
+    pub number: usize,
+    /// The reasoner model's `reasoning_content`, the agent's visible thought trace
+    pub thought: Option<String>,
+    pub content: Option<String>,
+    pub tool_calls: Vec<ToolInvocation>,
+    pub usage: Usage,
+}
+
+/// The outcome of [`Agent::run`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentRun {
+    /// The final answer, if the model gave one
+    pub answer: Option<String>,
+    pub stop_reason: StopReason,
+    pub steps: Vec<AgentStep>,
+    /// Usage of all steps together
+    pub usage: Usage,
+    /// The task and every message since, without the system prompt
+    pub messages: Vec<Message>,
+}
+
+type StepCallback = Arc<dyn Fn(&AgentStep) + Send + Sync>;
+
+/// A system prompt, tools and memory driven in a loop over chat completions
+///
+/// Without a final answer marker, a reply without tool calls is the final answer.
+/// With one, the answer is whatever follows the marker, and replies without it are
+/// answered with a reminder to finish.
+///
+/// The reasoner model doesn't support tools; use it for agents that only think and
+/// answer, with its reasoning reported as each step's `thought`.
+///
+/// # Example
+/// ```no_run
+/// use std::sync::Arc;
+/// use clia_deepseek_rs::{
+///     agent::{Agent, Memory, ToolRegistry},
+///     DeepSeekClient,
+/// };
+/// # #[tokio::main]
+/// # async fn main() {
+/// let tools = ToolRegistry::default().with_tool(
+///     "get_weather",
+///     "Returns the weather in a city",
+///     serde_json::json!({ "type": "object", "properties": { "city": { "type": "string" } } }),
+///     |_args: serde_json::Value| async { Ok("22°C and sunny".to_string()) },
+/// );
+/// let agent = Agent::new(DeepSeekClient::default().unwrap())
+///     .with_system_prompt("You plan trips.")
+///     .with_tools(tools)
+///     .with_memory(Arc::new(Memory::default()))
+///     .with_final_answer_marker("FINAL ANSWER:")
+///     .with_max_steps(6)
+///     .on_step(|step| println!("step {}: {:?}", step.number, step.content));
+/// let run = agent.run("Should I pack an umbrella for Lisbon?").await.unwrap();
+/// println!("{:?} ({} tokens)", run.answer, run.usage.total_tokens);
+/// # }
+/// ```
+pub struct Agent {
+    provider: Arc<dyn ChatProvider>,
+    system_prompt: Option<String>,
+    settings: RequestBody,
+    tools: ToolRegistry,
+    memory: Arc<Memory>,
+    memory_tools: bool,
+    max_steps: usize,
+    token_budget: Option<u32>,
+    final_answer_marker: Option<String>,
+    on_step: Option<StepCallback>,
+}
+
+impl Agent {
+    /// Creates an agent with no tools, limited to 10 steps
+    pub fn new(provider: impl ChatProvider + 'static) -> Self {
+        Agent {
+            provider: Arc::new(provider),
+            system_prompt: None,
+            settings: RequestBody::default(),
+            tools: ToolRegistry::default(),
+            memory: Arc::default(),
+            memory_tools: false,
+            max_steps: 10,
+            token_budget: None,
+            final_answer_marker: None,
+            on_step: None,
+        }
+    }
+
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Sets the model and sampling settings; messages and tools in `settings` are
+    /// ignored
+    pub fn with_settings(mut self, settings: RequestBody) -> Self {
+        self.settings = settings.with_messages(Vec::new());
+        self
+    }
+
+    pub fn with_tools(mut self, tools: ToolRegistry) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Shares `memory` with the agent and gives it `remember` and `forget` tools to
+    /// edit it
+    pub fn with_memory(mut self, memory: Arc<Memory>) -> Self {
+        self.memory = memory;
+        self.memory_tools = true;
+        self
+    }
+
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Stops the run once its steps have used `tokens` total tokens
+    pub fn with_token_budget(mut self, tokens: u32) -> Self {
+        self.token_budget = Some(tokens);
+        self
+    }
+
+    /// Treats the text after `marker` in a reply as the final answer
+    pub fn with_final_answer_marker(mut self, marker: impl Into<String>) -> Self {
+        self.final_answer_marker = Some(marker.into());
+        self
+    }
+
+    /// Calls `callback` after every step, e.g. to show progress
+    pub fn on_step(mut self, callback: impl Fn(&AgentStep) + Send + Sync + 'static) -> Self {
+        self.on_step = Some(Arc::new(callback));
+        self
+    }
+
+    /// Returns the agent's memory
+    pub fn memory(&self) -> &Arc<Memory> {
+        &self.memory
+    }
+
+    /// Works on `task` until a final answer or a limit is reached
+    ///
+    /// Tool failures are reported to the model rather than ending the run; request
+    /// errors end it.
+    pub async fn run(&self, task: impl Into<String>) -> Result<AgentRun, RequestErrors> {
+        let tools = self.tools();
+        let mut messages = vec![Message::new_user_message(task.into())];
+        let mut steps = Vec::new();
+        let mut usage = Usage::default();
+        for number in 1..=self.max_steps {
+            let mut history = self.system_message().into_iter().collect::<Vec<_>>();
+            history.extend(messages.iter().cloned());
+            let request = self
+                .settings
+                .clone()
+                .with_messages(history)
+                .with_tools(tools.definitions());
+            let response = self.provider.chat_completions(request).await?;
+            usage += &response.usage;
+            let choice = response.choices.into_iter().next().ok_or_else(|| {
+                RequestErrors::DecodeError("response without choices".to_string())
+            })?;
+            let mut reply = Message::from(choice.message);
+            let thought = reply.reasoning_content.take();
+            let calls = reply.tool_calls.clone().unwrap_or_default();
+            let content = Some(reply.content.clone()).filter(|c| !c.is_empty());
+            messages.push(reply);
+
+            let mut tool_calls = Vec::new();
+            for call in calls {
+                let output = tools.call(&call.function_call).await;
+                let result = match &output {
+                    Ok(output) => output.clone(),
+                    Err(e) => format!("Error: {}", e),
+                };
+                messages.push(Message::new_tool_message(result, call.id.clone()));
+                tool_calls.push(ToolInvocation {
+                    id: call.id,
+                    name: call.function_call.name,
+                    arguments: call.function_call.arguments,
+                    output,
+                });
+            }
+            let step = AgentStep {
+                number,
+                thought,
+                content,
+                tool_calls,
+                usage: response.usage,
+            };
+            if let Some(callback) = &self.on_step {
+                callback(&step);
+            }
+            let finished = step.tool_calls.is_empty();
+            let answer = finished
+                .then(|| self.final_answer(step.content.as_deref().unwrap_or_default()))
+                .flatten();
+            steps.push(step);
+
+            let stop_reason = if answer.is_some() {
+                Some(StopReason::FinalAnswer)
+            } else if self
+                .token_budget
+                .is_some_and(|budget| i64::from(usage.total_tokens) >= i64::from(budget))
+            {
+                Some(StopReason::TokenBudget)
+            } else {
+                None
+            };
+            if let Some(stop_reason) = stop_reason {
+                return Ok(AgentRun {
+                    answer,
+                    stop_reason,
+                    steps,
+                    usage,
+                    messages,
+                });
+            }
+            if finished {
+                if let Some(marker) = &self.final_answer_marker {
+                    messages.push(Message::new_user_message(format!(
+                        "Continue. When you are done, reply with `{}` followed by the answer.",
+                        marker
+                    )));
+                }
+            }
+        }
+        Ok(AgentRun {
+            answer: None,
+            stop_reason: StopReason::MaxSteps,
+            steps,
+            usage,
+            messages,
+        })
+    }
+
+    fn final_answer(&self, content: &str) -> Option<String> {
+        match &self.final_answer_marker {
+            Some(marker) => content
+                .find(marker.as_str())
+                .map(|start| content[start + marker.len()..].trim().to_string()),
+            None => Some(content.trim().to_string()),
+        }
+    }
+
+    /// Builds the system prompt, which is rebuilt every step to show current memory
+    fn system_message(&self) -> Option<Message> {
+        let mut sections: Vec<String> = self.system_prompt.iter().cloned().collect();
+        if let Some(marker) = &self.final_answer_marker {
+            sections.push(format!(
+                "When you have the final answer, reply with `{}` followed by it.",
+                marker
+            ));
+        }
+        if let Some(notes) = self.memory.render() {
+            sections.push(format!("## Memory\n{}", notes));
+        }
+        if sections.is_empty() {
+            return None;
+        }
+        Some(Message::new_system_message(sections.join("\n\n")))
+    }
+
+    fn tools(&self) -> ToolRegistry {
+        if !self.memory_tools {
+            return self.tools.clone();
+        }
+        let remember = self.memory.clone();
+        let forget = self.memory.clone();
+        self.tools
+            .clone()
+            .with_tool(
+                "remember",
+                "Stores a note that stays visible in later steps",
+                serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "key": { "type": "string" },
+                        "value": { "type": "string" }
+                    },
+                    "required": ["key", "value"]
+                }),
+                move |args: Value| {
+                    let memory = remember.clone();
+                    async move {
+                        let (Some(key), Some(value)) =
+                            (args["key"].as_str(), args["value"].as_str())
+                        else {
+                            return Err("`key` and `value` must be strings".to_string());
+                        };
+                        memory.set(key, value);
+                        Ok("Stored.".to_string())
+                    }
+                },
+            )
+            .with_tool(
+                "forget",
+                "Removes a stored note",
+                serde_json::json!({
+                    "type": "object",
+                    "properties": { "key": { "type": "string" } },
+                    "required": ["key"]
+                }),
+                move |args: Value| {
+                    let memory = forget.clone();
+                    async move {
+                        if memory.remove(args["key"].as_str().unwrap_or_default()) {
+                            Ok("Removed.".to_string())
+                        } else {
+                            Err("no such note".to_string())
+                        }
+                    }
+                },
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{
+        testing::{response, response_with, FakeProvider},
+        types::{request::Role, response::ChatCompletionsResponse},
+    };
+
+    /// Replies "..." once the scripted replies run out
+    fn script() -> FakeProvider {
+        FakeProvider::replying("...")
+    }
+
+    fn text(content: &str) -> Result<ChatCompletionsResponse, RequestErrors> {
+        Ok(response(content))
+    }
+
+    fn tool_call(name: &str, arguments: &str) -> Result<ChatCompletionsResponse, RequestErrors> {
+        Ok(response_with(
+            serde_json::json!({
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [{
+                    "id": "call_0",
+                    "type": "function",
+                    "function": { "name": name, "arguments": arguments }
+                }]
+            }),
+            "tool_calls",
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_tool_loop_and_marker() {
+        let script = script()
+            .then(tool_call("double", r#"{"n": 21}"#))
+            .then(text("Let me think."))
+            .then(text("Done. FINAL ANSWER: 42"));
+        let requests = script.requests();
+        let steps = Arc::new(Mutex::new(Vec::new()));
+        let seen = steps.clone();
+        let tools = ToolRegistry::default().with_tool(
+            "double",
+            "Doubles n",
+            serde_json::json!({}),
+            |args: Value| async move { Ok((args["n"].as_i64().unwrap_or(0) * 2).to_string()) },
+        );
+        let agent = Agent::new(script)
+            .with_system_prompt("Be exact.")
+            .with_tools(tools)
+            .with_final_answer_marker("FINAL ANSWER:")
+            .on_step(move |step| seen.lock().unwrap().push(step.number));
+        let run = agent.run("Double 21").await.unwrap();
+
+        assert_eq!(run.answer.as_deref(), Some("42"));
+        assert_eq!(run.stop_reason, StopReason::FinalAnswer);
+        assert_eq!(*steps.lock().unwrap(), [1, 2, 3]);
+        assert_eq!(run.steps[0].tool_calls[0].output, Ok("42".to_string()));
+        assert_eq!(run.usage.total_tokens, 6);
+
+        let requests = requests.lock().unwrap();
+        let first = requests[0].messages();
+        assert!(matches!(first[0].role, Role::System));
+        assert!(first[0].content.starts_with("Be exact."));
+        assert_eq!(requests[0].tools().len(), 1);
+        // The tool result, then the reminder after the reply without the marker
+        let last = requests[2].messages();
+        assert_eq!(last[3].tool_call_id.as_deref(), Some("call_0"));
+        assert_eq!(last[3].content, "42");
+        assert!(matches!(last[5].role, Role::User));
+    }
+
+    #[tokio::test]
+    async fn test_limits() {
+        let agent = Agent::new(script()).with_final_answer_marker("ANSWER:");
+        let run = agent.with_max_steps(2).run("task").await.unwrap();
+        assert_eq!(run.stop_reason, StopReason::MaxSteps);
+        assert_eq!(run.steps.len(), 2);
+
+        let agent = Agent::new(script())
+            .with_final_answer_marker("ANSWER:")
+            .with_token_budget(5);
+        let run = agent.run("task").await.unwrap();
+        assert_eq!(run.stop_reason, StopReason::TokenBudget);
+        assert_eq!(run.steps.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_thought_and_plain_answer() {
+        let script = script().then(Ok(response_with(
+            serde_json::json!({
+                "role": "assistant",
+                "content": " 105 ",
+                "reasoning_content": "15 * 7 = 105"
+            }),
+            "stop",
+        )));
+        let run = Agent::new(script).run("What is 15 * 7?").await.unwrap();
+        assert_eq!(run.answer.as_deref(), Some("105"));
+        assert_eq!(run.steps[0].thought.as_deref(), Some("15 * 7 = 105"));
+        assert!(run.messages[1].reasoning_content.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_memory_tools() {
+        let script = script()
+            .then(tool_call(
+                "remember",
+                r#"{"key": "city", "value": "Lisbon"}"#,
+            ))
+            .then(text("Noted."));
+        let requests = script.requests();
+        let memory = Arc::new(Memory::default());
+        let agent = Agent::new(script).with_memory(memory.clone());
+        agent.run("I live in Lisbon").await.unwrap();
+        assert_eq!(memory.get("city").as_deref(), Some("Lisbon"));
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0].tools().len(), 2);
+        assert!(requests[1].messages()[0].content.contains("- city: Lisbon"));
+    }
+}
@@ -0,0 +1,129 @@
+//! Functions an agent can call
+
+use std::{collections::BTreeMap, future::Future, sync::Arc};
+
+use futures::future::BoxFuture;
+use serde_json::Value;
+
+use crate::types::{request::Tool, response::FunctionCall};
+
+/// Runs a tool with the arguments chosen by the model
+///
+/// The output, or the error message, is sent back to the model as the tool's result.
+/// Implemented for async closures taking the parsed arguments.
+pub trait ToolHandler: Send + Sync {
+    fn call(&self, arguments: Value) -> BoxFuture<'_, Result<String, String>>;
+}
+
+impl<F, Fut> ToolHandler for F
+where
+    F: Fn(Value) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<String, String>> + Send + 'static,
+{
+    fn call(&self, arguments: Value) -> BoxFuture<'_, Result<String, String>> {
+        Box::pin(self(arguments))
+    }
+}
+
+/// The tools an agent offers the model, by name
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::agent::ToolRegistry;
+///
+/// let tools = ToolRegistry::default().with_tool(
+///     "add",
+///     "Adds two numbers",
+///     serde_json::json!({
+///         "type": "object",
+///         "properties": { "a": { "type": "number" }, "b": { "type": "number" } }
+///     }),
+///     |args: serde_json::Value| async move {
+///         let sum = args["a"].as_f64().unwrap_or(0.0) + args["b"].as_f64().unwrap_or(0.0);
+///         Ok(sum.to_string())
+///     },
+/// );
+/// assert_eq!(tools.definitions()[0].function.name, "add");
+/// ```
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: BTreeMap<String, (Tool, Arc<dyn ToolHandler>)>,
+}
+
+impl ToolRegistry {
+    /// Adds a tool, replacing any tool with the same name
+    pub fn with_tool(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: Value,
+        handler: impl ToolHandler + 'static,
+    ) -> Self {
+        let name = name.into();
+        let tool = Tool::function(name.clone(), description, parameters);
+        self.tools.insert(name, (tool, Arc::new(handler)));
+        self
+    }
+
+    /// Returns the definitions sent with each request
+    pub fn definitions(&self) -> Vec<Tool> {
+        self.tools.values().map(|(tool, _)| tool.clone()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    /// Runs the tool named in `call`
+    ///
+    /// Unknown tools and arguments that aren't valid JSON are reported as errors, so the
+    /// model can correct itself.
+    pub async fn call(&self, call: &FunctionCall) -> Result<String, String> {
+        let Some((_, handler)) = self.tools.get(&call.name) else {
+            return Err(format!("unknown tool `{}`", call.name));
+        };
+        let arguments = match call.arguments.trim() {
+            "" => Value::Object(Default::default()),
+            arguments => {
+                serde_json::from_str(arguments).map_err(|e| format!("invalid arguments: {}", e))?
+            }
+        };
+        handler.call(arguments).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str, arguments: &str) -> FunctionCall {
+        FunctionCall {
+            name: name.to_string(),
+            arguments: arguments.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call() {
+        let tools = ToolRegistry::default().with_tool(
+            "echo",
+            "Echoes its input",
+            serde_json::json!({}),
+            |args: Value| async move { Ok(args["text"].as_str().unwrap_or_default().to_string()) },
+        );
+        assert_eq!(
+            tools.call(&call("echo", r#"{"text":"hi"}"#)).await,
+            Ok("hi".to_string())
+        );
+        assert_eq!(tools.call(&call("echo", "")).await, Ok(String::new()));
+        assert!(tools
+            .call(&call("echo", "{"))
+            .await
+            .unwrap_err()
+            .starts_with("invalid arguments"));
+        assert_eq!(
+            tools.call(&call("nope", "{}")).await,
+            Err("unknown tool `nope`".to_string())
+        );
+    }
+}
@@ -0,0 +1,47 @@
+//! OpenAI-compatible proxy in front of the DeepSeek API
+//!
+//! Configuration comes from the environment (or a `.env` file):
+//! - `DEEP_SEEK_API_KEY`: upstream API key
+//! - `DEEP_SEEK_PROXY_ADDR`: listen address, defaults to `127.0.0.1:8080`
+//! - `DEEP_SEEK_PROXY_ALIASES`: `alias=model` pairs, e.g. `gpt-4o=deepseek-chat,o1=deepseek-reasoner`
+//! - `DEEP_SEEK_PROXY_CALLERS`: `token=name` pairs; when set, callers must authenticate
+
+use std::sync::Arc;
+
+use clia_deepseek_rs::{
+    proxy::{self, ProxyConfig, StderrUsageLog},
+    request::Model,
+    DeepSeekClient,
+};
+
+fn pairs(var: &str) -> Vec<(String, String)> {
+    std::env::var(var)
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect()
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenvy::dotenv().ok();
+
+    let mut config = ProxyConfig::default();
+    for (alias, model) in pairs("DEEP_SEEK_PROXY_ALIASES") {
+        let model: Model = serde_json::from_value(serde_json::Value::String(model))?;
+        config = config.with_alias(alias, model);
+    }
+    for (token, name) in pairs("DEEP_SEEK_PROXY_CALLERS") {
+        config = config.with_caller(token, name);
+    }
+
+    let addr =
+        std::env::var("DEEP_SEEK_PROXY_ADDR").unwrap_or_else(|_| "127.0.0.1:8080".to_string());
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    eprintln!("listening on {}", addr);
+
+    let client = Arc::new(DeepSeekClient::default()?);
+    proxy::serve(listener, client, config, Arc::new(StderrUsageLog)).await?;
+    Ok(())
+}
@@ -0,0 +1,319 @@
+//! A synchronous client for code without an async runtime
+//!
+//! [`DeepSeekClient`] wraps the async [`crate::DeepSeekClient`] and drives it on its own
+//! single-threaded tokio runtime, so it shares every request and response type. Its
+//! methods must not be called from within an async runtime.
+//!
+//! It covers what the async client offers: chat completions, streaming through an
+//! iterator, and hedged calls. The crate has no FIM, model listing or balance
+//! endpoints yet.
+
+use std::{path::Path, sync::Arc, time::Duration};
+
+use futures::StreamExt;
+use tokio::runtime::Runtime;
+
+use crate::{
+    client::{
+        breaker::{BreakerState, CircuitBreaker},
+        cache::ResponseCache,
+        chat_completions::{
+            request::RequestBody,
+            response::{ChatCompletionsChunk, ChatCompletionsResponse},
+            stream::{CancellationToken, ChatCompletionsStream},
+        },
+        client::ClientInitErrors,
+        hedge::{HedgePolicy, HedgedResponse},
+        keys::KeyProvider,
+        middleware::Middleware,
+        observer::Observer,
+        transport::Transport,
+    },
+    errors::request_errors::RequestErrors,
+};
+
+/// Blocking counterpart of [`crate::DeepSeekClient`]
+///
+/// # Example
+/// ```no_run
+/// use clia_deepseek_rs::{blocking::DeepSeekClient, request::{Message, RequestBody}};
+///
+/// let client = DeepSeekClient::default().unwrap();
+/// let request = RequestBody::new_messages(vec![Message::new_user_message("Hello".to_string())]);
+/// let response = client.chat_completions(request.clone()).unwrap();
+/// println!("{}", response.choices[0].message.content.clone().unwrap_or_default());
+///
+/// for chunk in client.chat_completions_stream(request).unwrap() {
+///     print!("{}", chunk.unwrap().choices[0].delta.content.clone().unwrap_or_default());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct DeepSeekClient {
+    inner: crate::DeepSeekClient,
+    runtime: Arc<Runtime>,
+}
+
+/// Wraps a configured async client
+///
+/// # Panics
+/// Panics if the runtime can't be created, like `reqwest::blocking::Client::new` does
+/// when its backend fails to initialize.
+impl From<crate::DeepSeekClient> for DeepSeekClient {
+    fn from(inner: crate::DeepSeekClient) -> Self {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("failed to create the blocking client's runtime");
+        DeepSeekClient {
+            inner,
+            runtime: Arc::new(runtime),
+        }
+    }
+}
+
+impl DeepSeekClient {
+    pub fn new_with_api_key(api_key: String) -> Self {
+        crate::DeepSeekClient::new_with_api_key(api_key).into()
+    }
+    pub fn new_with_url_and_api_key(url: String, api_key: String) -> Self {
+        crate::DeepSeekClient::new_with_url_and_api_key(url, api_key).into()
+    }
+    /// Creates a client that asks `provider` for the API key on every request
+    pub fn new_with_key_provider(provider: impl KeyProvider + 'static) -> Self {
+        crate::DeepSeekClient::new_with_key_provider(provider).into()
+    }
+    pub fn new_with_url_and_key_provider(
+        url: String,
+        provider: impl KeyProvider + 'static,
+    ) -> Self {
+        crate::DeepSeekClient::new_with_url_and_key_provider(url, provider).into()
+    }
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Result<Self, ClientInitErrors> {
+        crate::DeepSeekClient::default().map(Self::from)
+    }
+    /// Creates a client with the key stored in the environment variable `var`
+    pub fn from_env_var(var: &str) -> Result<Self, ClientInitErrors> {
+        crate::DeepSeekClient::from_env_var(var).map(Self::from)
+    }
+    /// Creates a client with `DEEP_SEEK_API_KEY` from the environment or a `.env` file
+    #[cfg(feature = "dotenv")]
+    pub fn from_dotenv() -> Result<Self, ClientInitErrors> {
+        crate::DeepSeekClient::from_dotenv().map(Self::from)
+    }
+    /// Creates a client with the key stored in a file
+    pub fn from_key_file(path: impl AsRef<Path>) -> Result<Self, ClientInitErrors> {
+        crate::DeepSeekClient::from_key_file(path).map(Self::from)
+    }
+    /// Replaces the key provider with a single fixed key; safe to call on a shared client
+    pub fn set_api_key(&self, api_key: String) {
+        self.inner.set_api_key(api_key);
+    }
+    /// Uses a single fixed key; unlike [`Self::set_api_key`], clones made before this
+    /// call keep their key
+    pub fn with_api_key(self, api_key: String) -> Self {
+        self.map(|inner| inner.with_api_key(api_key))
+    }
+    /// Replaces the key provider; safe to call on a shared client
+    pub fn set_key_provider(&self, provider: impl KeyProvider + 'static) {
+        self.inner.set_key_provider(provider);
+    }
+    /// Uses `provider` for keys; unlike [`Self::set_key_provider`], clones made before
+    /// this call keep their provider
+    pub fn with_key_provider(self, provider: impl KeyProvider + 'static) -> Self {
+        self.map(|inner| inner.with_key_provider(provider))
+    }
+    /// Adds an observer notified of every request, response, error and streamed chunk
+    pub fn with_observer(self, observer: impl Observer + 'static) -> Self {
+        self.map(|inner| inner.with_observer(observer))
+    }
+    /// Adds a middleware layer around every chat completion call
+    pub fn with_middleware(self, middleware: impl Middleware + 'static) -> Self {
+        self.map(|inner| inner.with_middleware(middleware))
+    }
+    /// Serves repeated requests from `cache`; shorthand for adding it as middleware
+    pub fn with_cache(self, cache: ResponseCache) -> Self {
+        self.map(|inner| inner.with_cache(cache))
+    }
+    /// Sends requests through `transport` instead of the default reqwest client
+    pub fn with_transport(self, transport: impl Transport + 'static) -> Self {
+        self.map(|inner| inner.with_transport(transport))
+    }
+    /// Fails requests that take longer than `timeout` in total
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.map(|inner| inner.with_timeout(timeout))
+    }
+    /// Interrupts streams that go `timeout` without a chunk
+    pub fn with_stream_idle_timeout(self, timeout: Duration) -> Self {
+        self.map(|inner| inner.with_stream_idle_timeout(timeout))
+    }
+    /// Fails requests fast while the backend keeps failing
+    pub fn with_circuit_breaker(self, breaker: CircuitBreaker) -> Self {
+        self.map(|inner| inner.with_circuit_breaker(breaker))
+    }
+    /// Sets when `chat_completions_hedged` sends a duplicate request
+    pub fn with_hedging(self, policy: HedgePolicy) -> Self {
+        self.map(|inner| inner.with_hedging(policy))
+    }
+    /// Makes identical requests from several threads share one call while it is in flight
+    pub fn with_deduplication(self) -> Self {
+        self.map(|inner| inner.with_deduplication())
+    }
+    /// Returns the circuit breaker's state, if one is set
+    pub fn circuit_state(&self) -> Option<BreakerState> {
+        self.inner.circuit_state()
+    }
+    /// Returns the current key provider
+    pub fn key_provider(&self) -> Arc<dyn KeyProvider> {
+        self.inner.key_provider()
+    }
+    /// Returns the async client this client drives
+    pub fn inner(&self) -> &crate::DeepSeekClient {
+        &self.inner
+    }
+
+    fn map(self, f: impl FnOnce(crate::DeepSeekClient) -> crate::DeepSeekClient) -> Self {
+        DeepSeekClient {
+            inner: f(self.inner),
+            runtime: self.runtime,
+        }
+    }
+
+    /// Sends a chat completion request and waits for the response
+    pub fn chat_completions(
+        &self,
+        request: RequestBody,
+    ) -> Result<ChatCompletionsResponse, RequestErrors> {
+        self.runtime.block_on(self.inner.chat_completions(request))
+    }
+
+    /// Sends a chat completion request, hedged according to the client's policy
+    pub fn chat_completions_hedged(
+        &self,
+        request: RequestBody,
+    ) -> Result<HedgedResponse, RequestErrors> {
+        self.runtime
+            .block_on(self.inner.chat_completions_hedged(request))
+    }
+
+    /// Sends a streaming chat completion request and returns an iterator over the chunks
+    pub fn chat_completions_stream(
+        &self,
+        request: RequestBody,
+    ) -> Result<ChatCompletionsIter, RequestErrors> {
+        let stream = self
+            .runtime
+            .block_on(self.inner.chat_completions_stream(request))?;
+        Ok(self.iter(stream))
+    }
+
+    /// Like [`Self::chat_completions_stream`], but stops when `cancel` fires, e.g. from
+    /// another thread
+    pub fn chat_completions_stream_with_cancellation(
+        &self,
+        request: RequestBody,
+        cancel: CancellationToken,
+    ) -> Result<ChatCompletionsIter, RequestErrors> {
+        let stream = self.runtime.block_on(
+            self.inner
+                .chat_completions_stream_with_cancellation(request, cancel),
+        )?;
+        Ok(self.iter(stream))
+    }
+
+    fn iter(&self, stream: ChatCompletionsStream) -> ChatCompletionsIter {
+        ChatCompletionsIter {
+            stream,
+            runtime: self.runtime.clone(),
+        }
+    }
+}
+
+/// Blocking iterator over the chunks of a streamed chat completion
+pub struct ChatCompletionsIter {
+    stream: ChatCompletionsStream,
+    runtime: Arc<Runtime>,
+}
+
+impl Iterator for ChatCompletionsIter {
+    type Item = Result<ChatCompletionsChunk, RequestErrors>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.stream.next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        client::{
+            cache::MemoryCache,
+            chat_completions::{request::Message, stream::StreamAccumulator},
+            transport::MemoryTransport,
+        },
+        testing::{response, response_json},
+    };
+
+    fn api_key(client: &DeepSeekClient) -> String {
+        let key = client.key_provider().key().unwrap();
+        key.expose_secret().to_string()
+    }
+
+    #[test]
+    fn test_with_api_key_detaches_clone() {
+        let inner = crate::DeepSeekClient::new_with_api_key("a".to_string());
+        let base = DeepSeekClient::from(inner.clone());
+        let other = DeepSeekClient::from(inner).with_api_key("b".to_string());
+        assert_eq!(api_key(&base), "a");
+        assert_eq!(api_key(&other), "b");
+        base.set_api_key("c".to_string());
+        assert_eq!(api_key(&other), "b");
+    }
+
+    #[test]
+    fn test_blocking_chat_and_stream_over_transport() {
+        let transport = Arc::new(MemoryTransport::new());
+        transport.push_json(&response_json("4"));
+        let chunk = r#"{"id":"1","object":"chat.completion.chunk","created":1,"model":"deepseek-chat","choices":[{"index":0,"delta":{"content":"4"},"finish_reason":null}]}"#;
+        transport.push_response(
+            http::StatusCode::OK,
+            format!("data: {chunk}\n\ndata: [DONE]\n\n"),
+        );
+        let client =
+            DeepSeekClient::new_with_api_key("key".to_string()).with_transport(transport.clone());
+        let request = RequestBody::new_messages(vec![Message::new_user_message("2+2".to_string())]);
+
+        let response = client.chat_completions(request.clone()).unwrap();
+        assert_eq!(response.choices[0].message.content.as_deref(), Some("4"));
+
+        let mut accumulator = StreamAccumulator::new();
+        for chunk in client.chat_completions_stream(request).unwrap() {
+            accumulator.push(&chunk.unwrap());
+        }
+        assert_eq!(accumulator.content(), "4");
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        let streamed: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+        assert_eq!(streamed["stream"], true);
+    }
+
+    #[test]
+    fn test_blocking_chat_and_stream_from_cache() {
+        let request = RequestBody::new_messages(vec![Message::new_user_message("2+2".to_string())]);
+        let response = response("4");
+        let cache = ResponseCache::new(MemoryCache::new(10));
+        cache.put(&request, &response);
+        let client = DeepSeekClient::new_with_api_key("key".to_string()).with_cache(cache);
+
+        let hit = client.chat_completions(request.clone()).unwrap();
+        assert_eq!(hit.choices[0].message.content.as_deref(), Some("4"));
+
+        let mut accumulator = StreamAccumulator::new();
+        for chunk in client.chat_completions_stream(request).unwrap() {
+            accumulator.push(&chunk.unwrap());
+        }
+        assert_eq!(accumulator.content(), "4");
+    }
+}
@@ -0,0 +1,177 @@
+//! Circuit breakers that stop sending requests to a failing backend
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::errors::request_errors::RequestErrors;
+
+/// Returns whether `error` says the backend itself is unhealthy, as opposed to a
+/// problem with the request or the key
+pub fn is_backend_failure(error: &RequestErrors) -> bool {
+    match error {
+        RequestErrors::ConnectionError(_) | RequestErrors::TimeoutError(_) => true,
+        RequestErrors::StatusError(status, _) => status.is_server_error(),
+        _ => false,
+    }
+}
+
+/// State of a [`CircuitBreaker`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Requests flow normally
+    Closed,
+    /// Requests fail fast until the cooldown ends
+    Open,
+    /// A single probe request is allowed through to test the backend
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct BreakerInner {
+    state: BreakerState,
+    consecutive_failures: u32,
+    /// When the breaker opened, or when the current probe started while half-open
+    since: Instant,
+}
+
+/// Opens after a number of consecutive backend failures and lets a probe through once
+/// the cooldown has passed
+///
+/// A successful probe closes the breaker; a failed one opens it for another cooldown.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use clia_deepseek_rs::client::breaker::{BreakerState, CircuitBreaker};
+///
+/// let breaker = CircuitBreaker::new(2, Duration::from_secs(30));
+/// breaker.record_failure();
+/// breaker.record_failure();
+/// assert_eq!(breaker.state(), BreakerState::Open);
+/// assert!(!breaker.allow());
+/// ```
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<BreakerInner>,
+}
+
+impl Default for CircuitBreaker {
+    /// Opens after 5 consecutive failures for 30 seconds
+    fn default() -> Self {
+        CircuitBreaker::new(5, Duration::from_secs(30))
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        CircuitBreaker {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            inner: Mutex::new(BreakerInner {
+                state: BreakerState::Closed,
+                consecutive_failures: 0,
+                since: Instant::now(),
+            }),
+        }
+    }
+
+    /// Returns whether a request may be sent now
+    ///
+    /// Once the cooldown has passed this admits one probe; if the probe never reports
+    /// back, another is admitted after a further cooldown.
+    pub fn allow(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            BreakerState::Closed => true,
+            BreakerState::Open | BreakerState::HalfOpen => {
+                if inner.since.elapsed() < self.cooldown {
+                    return false;
+                }
+                inner.state = BreakerState::HalfOpen;
+                inner.since = Instant::now();
+                true
+            }
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.state = BreakerState::Closed;
+        inner.consecutive_failures = 0;
+    }
+
+    pub fn record_failure(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures += 1;
+        if inner.state == BreakerState::HalfOpen
+            || inner.consecutive_failures >= self.failure_threshold
+        {
+            inner.state = BreakerState::Open;
+            inner.since = Instant::now();
+        }
+    }
+
+    /// Records the outcome of a request
+    ///
+    /// Only backend failures count against it. Other errors, like a bad request or a
+    /// rate limit, say nothing about the backend's health and leave the breaker as it
+    /// is; a half-open breaker keeps waiting for a real probe.
+    pub fn record<T>(&self, result: &Result<T, RequestErrors>) {
+        match result {
+            Ok(_) => self.record_success(),
+            Err(e) if is_backend_failure(e) => self.record_failure(),
+            Err(_) => {}
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.inner.lock().unwrap().state
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.inner.lock().unwrap().consecutive_failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+
+    #[test]
+    fn test_open_and_half_open() {
+        let breaker = CircuitBreaker::new(2, Duration::from_millis(20));
+        let unavailable =
+            RequestErrors::StatusError(StatusCode::SERVICE_UNAVAILABLE, String::new());
+        breaker.record::<()>(&Err(unavailable));
+        breaker.record::<()>(&Err(RequestErrors::BadRequest(String::new())));
+        assert_eq!(breaker.consecutive_failures(), 1);
+        breaker.record::<()>(&Ok(()));
+        assert_eq!(breaker.consecutive_failures(), 0);
+
+        breaker.record_failure();
+        breaker.record_failure();
+        assert!(!breaker.allow());
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(breaker.allow());
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert!(!breaker.allow());
+        // A rate-limited probe doesn't close the breaker
+        breaker.record::<()>(&Err(RequestErrors::RateLimitExceeded(String::new())));
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert_eq!(breaker.consecutive_failures(), 2);
+
+        breaker.record_failure();
+        assert_eq!(breaker.state(), BreakerState::Open);
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(breaker.allow());
+        breaker.record_success();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert!(breaker.allow());
+    }
+}
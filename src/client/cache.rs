@@ -0,0 +1,410 @@
+//! Response caching keyed on normalized requests
+//!
+//! [`ResponseCache`] is a [`Middleware`] that serves repeated requests from a
+//! [`CacheBackend`] without calling the API. Responses served from the cache have
+//! [`ChatCompletionsResponse::cache_hit`] set so their `Usage` isn't counted twice.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, SystemTime},
+};
+
+use http::HeaderMap;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    chat_completions::{request::RequestBody, response::ChatCompletionsResponse},
+    dedupe::fingerprint,
+    middleware::Middleware,
+};
+use crate::errors::request_errors::RequestErrors;
+
+/// Returns a stable key for `request`
+///
+/// This is the request's [`fingerprint`], so streamed and non-streamed requests share
+/// entries.
+pub fn cache_key(request: &RequestBody) -> String {
+    fingerprint(request)
+}
+
+/// A cached response and when it was stored
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CacheEntry {
+    pub response: ChatCompletionsResponse,
+    pub stored_at: SystemTime,
+}
+
+/// Storage for cached responses
+pub trait CacheBackend: Send + Sync {
+    fn get(&self, key: &str) -> Option<CacheEntry>;
+    fn put(&self, key: &str, entry: CacheEntry);
+    fn remove(&self, key: &str);
+}
+
+#[derive(Debug, Default)]
+struct LruState {
+    entries: HashMap<String, (CacheEntry, u64)>,
+    order: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl LruState {
+    fn touch(&mut self, key: &str) {
+        self.tick += 1;
+        let tick = self.tick;
+        if let Some((_, used)) = self.entries.get_mut(key) {
+            self.order.remove(used);
+            *used = tick;
+            self.order.insert(tick, key.to_string());
+        }
+    }
+}
+
+/// In-memory backend evicting the least recently used entry when full
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    state: Mutex<LruState>,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: usize) -> Self {
+        MemoryCache {
+            capacity,
+            state: Mutex::new(LruState::default()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl CacheBackend for MemoryCache {
+    fn get(&self, key: &str) -> Option<CacheEntry> {
+        let mut state = self.state.lock().unwrap();
+        state.touch(key);
+        state.entries.get(key).map(|(entry, _)| entry.clone())
+    }
+
+    fn put(&self, key: &str, entry: CacheEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if let Some((stored, _)) = state.entries.get_mut(key) {
+            *stored = entry;
+        } else {
+            if state.entries.len() >= self.capacity {
+                if let Some((_, oldest)) = state.order.pop_first() {
+                    state.entries.remove(&oldest);
+                }
+            }
+            state.entries.insert(key.to_string(), (entry, 0));
+        }
+        state.touch(key);
+    }
+
+    fn remove(&self, key: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some((_, used)) = state.entries.remove(key) {
+            state.order.remove(&used);
+        }
+    }
+}
+
+/// On-disk backend storing one JSON file per entry
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    /// Uses `dir` for cache files, creating it if needed
+    pub fn new(dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(DiskCache { dir })
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+impl CacheBackend for DiskCache {
+    fn get(&self, key: &str) -> Option<CacheEntry> {
+        let contents = std::fs::read(self.path(key)).ok()?;
+        serde_json::from_slice(&contents).ok()
+    }
+
+    fn put(&self, key: &str, entry: CacheEntry) {
+        let Ok(contents) = serde_json::to_vec(&entry) else {
+            return;
+        };
+        // Write then rename so readers never see a partial file; the counter keeps
+        // concurrent writers of one key in this process apart
+        static WRITES: AtomicU64 = AtomicU64::new(0);
+        let tmp = self.dir.join(format!(
+            "{}.{}.{}.tmp",
+            key,
+            std::process::id(),
+            WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+        if std::fs::write(&tmp, contents).is_ok() {
+            let _ = std::fs::rename(&tmp, self.path(key));
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        let _ = std::fs::remove_file(self.path(key));
+    }
+}
+
+/// Serves repeated requests from a [`CacheBackend`]
+///
+/// Successful non-streaming responses are stored. Streaming requests are served from
+/// the cache as a single chunk but never stored. Requests built with
+/// [`RequestBody::with_cache_bypass`] skip the cache entirely.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use clia_deepseek_rs::{client::cache::{MemoryCache, ResponseCache}, DeepSeekClient};
+///
+/// let client = DeepSeekClient::new_with_api_key("key".to_string())
+///     .with_cache(ResponseCache::new(MemoryCache::new(1000)).with_ttl(Duration::from_secs(3600)));
+/// ```
+pub struct ResponseCache {
+    backend: Box<dyn CacheBackend>,
+    ttl: Option<Duration>,
+}
+
+impl ResponseCache {
+    pub fn new(backend: impl CacheBackend + 'static) -> Self {
+        ResponseCache {
+            backend: Box::new(backend),
+            ttl: None,
+        }
+    }
+
+    /// Treats entries older than `ttl` as missing
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Looks up a live entry for `request`
+    pub fn get(&self, request: &RequestBody) -> Option<ChatCompletionsResponse> {
+        let key = cache_key(request);
+        let entry = self.backend.get(&key)?;
+        let expired = self.ttl.is_some_and(|ttl| {
+            entry
+                .stored_at
+                .elapsed()
+                .map_or(true, |elapsed| elapsed > ttl)
+        });
+        if expired {
+            self.backend.remove(&key);
+            return None;
+        }
+        let mut response = entry.response;
+        response.cache_hit = true;
+        Some(response)
+    }
+
+    /// Stores `response` for `request`
+    pub fn put(&self, request: &RequestBody, response: &ChatCompletionsResponse) {
+        let mut response = response.clone();
+        response.cache_hit = false;
+        let entry = CacheEntry {
+            response,
+            stored_at: SystemTime::now(),
+        };
+        self.backend.put(&cache_key(request), entry);
+    }
+}
+
+impl Middleware for ResponseCache {
+    fn on_request(
+        &self,
+        request: &mut RequestBody,
+        _headers: &mut HeaderMap,
+    ) -> Option<Result<ChatCompletionsResponse, RequestErrors>> {
+        if request.cache_bypass() {
+            return None;
+        }
+        self.get(request).map(Ok)
+    }
+
+    fn on_response(
+        &self,
+        request: &RequestBody,
+        result: &mut Result<ChatCompletionsResponse, RequestErrors>,
+    ) {
+        if let Ok(response) = result {
+            if !response.cache_hit && !request.cache_bypass() {
+                self.put(request, response);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::chat_completions::{
+        request::{Message, Role, Temperature},
+        response::{ChatCompletionsChoices, FinishReasons, Message as ResponseMessage, Usage},
+    };
+
+    fn request(prompt: &str) -> RequestBody {
+        RequestBody::new_messages(vec![Message::new_user_message(prompt.to_string())])
+            .with_temperature(Temperature::new(0.0))
+    }
+
+    fn response(id: &str) -> ChatCompletionsResponse {
+        ChatCompletionsResponse {
+            id: id.to_string(),
+            choices: vec![ChatCompletionsChoices {
+                finish_reason: FinishReasons::Stop,
+                index: 0,
+                message: ResponseMessage {
+                    content: Some("4".to_string()),
+                    reasoning_content: None,
+                    role: Role::Assistant,
+                    tool_calls: None,
+                },
+            }],
+            created: 0,
+            model: "deepseek-chat".to_string(),
+            object: "chat.completion".to_string(),
+            usage: Usage {
+                completion_tokens: 1,
+                prompt_tokens: 5,
+                prompt_cache_hit_tokens: 0,
+                prompt_cache_miss_tokens: 5,
+                total_tokens: 6,
+                completion_tokens_details: None,
+                prompt_tokens_details: None,
+            },
+            cache_hit: false,
+        }
+    }
+
+    fn entry(id: &str) -> CacheEntry {
+        CacheEntry {
+            response: response(id),
+            stored_at: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn test_cache_key() {
+        assert_eq!(cache_key(&request("2+2")), cache_key(&request("2+2")));
+        assert_eq!(
+            cache_key(&request("2+2")),
+            cache_key(&request("2+2").with_stream(true))
+        );
+        assert_ne!(cache_key(&request("2+2")), cache_key(&request("2+3")));
+        assert_eq!(cache_key(&request("2+2")).len(), 64);
+    }
+
+    #[test]
+    fn test_memory_cache_lru() {
+        let cache = MemoryCache::new(2);
+        cache.put("a", entry("a"));
+        cache.put("b", entry("b"));
+        assert!(cache.get("a").is_some());
+        cache.put("c", entry("c"));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("c").is_some());
+        cache.remove("a");
+        assert!(cache.get("a").is_none());
+    }
+
+    #[test]
+    fn test_memory_cache_reput_then_evict() {
+        let cache = MemoryCache::new(2);
+        cache.put("a", entry("a"));
+        cache.put("b", entry("b"));
+        cache.put("a", entry("a2"));
+        cache.put("c", entry("c"));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("b").is_none());
+        assert_eq!(cache.get("a").unwrap().response.id, "a2");
+        cache.put("d", entry("d"));
+        cache.put("e", entry("e"));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_disk_cache_concurrent_puts() {
+        let dir = std::env::temp_dir().join(format!("deepseek_cache_race_{}", std::process::id()));
+        let cache = DiskCache::new(&dir).unwrap();
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| cache.put("a", entry("a")));
+            }
+        });
+        assert_eq!(cache.get("a").unwrap().response.id, "a");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_disk_cache() {
+        let dir = std::env::temp_dir().join(format!("deepseek_cache_{}", std::process::id()));
+        let cache = DiskCache::new(&dir).unwrap();
+        cache.put("a", entry("a"));
+        assert_eq!(cache.get("a").unwrap().response.id, "a");
+        cache.remove("a");
+        assert!(cache.get("a").is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_response_cache_ttl_and_marker() {
+        let cache = ResponseCache::new(MemoryCache::new(10));
+        cache.put(&request("2+2"), &response("a"));
+        let hit = cache.get(&request("2+2")).unwrap();
+        assert!(hit.cache_hit);
+        assert_eq!(hit.id, "a");
+
+        let cache = ResponseCache::new(MemoryCache::new(10)).with_ttl(Duration::ZERO);
+        cache.put(&request("2+2"), &response("a"));
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(cache.get(&request("2+2")).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_client_serves_from_cache() {
+        let cache = ResponseCache::new(MemoryCache::new(10));
+        cache.put(&request("2+2"), &response("a"));
+        let client = crate::DeepSeekClient::new_with_api_key("key".to_string()).with_cache(cache);
+        let hit = client.chat_completions(request("2+2")).await.unwrap();
+        assert!(hit.cache_hit);
+    }
+
+    #[test]
+    fn test_bypass() {
+        let cache = ResponseCache::new(MemoryCache::new(10));
+        cache.put(&request("2+2"), &response("a"));
+        let mut bypass = request("2+2").with_cache_bypass(true);
+        assert_eq!(cache_key(&bypass), cache_key(&request("2+2")));
+        assert!(cache
+            .on_request(&mut bypass, &mut HeaderMap::new())
+            .is_none());
+    }
+}
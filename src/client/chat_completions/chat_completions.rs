@@ -1,17 +1,29 @@
 //! Chat completions API implementation
 
+use http::{HeaderMap, Method, StatusCode};
+
 use super::request::RequestBody;
 use crate::{
-    client::{chat_completions::response::ChatCompletionsResponse, client::DeepSeekClient},
+    client::{
+        chat_completions::response::ChatCompletionsResponse,
+        client::DeepSeekClient,
+        dedupe::fingerprint,
+        keys::{is_key_error, ApiKey},
+        middleware::Pipeline,
+        observer::Call,
+        transport::{HttpRequest, HttpResponse},
+    },
     errors::request_errors::RequestErrors,
 };
 
+pub(crate) const ENDPOINT: &str = "chat/completions";
+
 impl DeepSeekClient {
     /// Sends a chat completion request to the DeepSeek API
     ///
     /// # Example
     /// ```no_run
-    /// use deepseek_rs::{DeepSeekClient, client::chat_completions::request::{RequestBody, Message}};
+    /// use clia_deepseek_rs::{DeepSeekClient, client::chat_completions::request::{RequestBody, Message}};
     /// # use tokio;
     /// # #[tokio::main]
     /// # async fn main() {
@@ -26,54 +38,299 @@ impl DeepSeekClient {
         &self,
         request: RequestBody,
     ) -> Result<ChatCompletionsResponse, RequestErrors> {
-        let url = format!("{}/chat/completions", self.url);
-        let headers = self.default_headers();
-        let res = self
-            .client
-            .post(&url)
-            .headers(headers)
-            .json(&request)
-            .send()
+        let Some(in_flight) = &self.in_flight else {
+            return self.run_chat_completions(request).await;
+        };
+        let key = fingerprint(&request);
+        in_flight
+            .run(key, || {
+                let client = self.clone();
+                async move { client.run_chat_completions(request).await }
+            })
             .await
-            .map_err(|e| RequestErrors::from(e))?;
-        match res.status() {
-            reqwest::StatusCode::OK => {}
-            reqwest::StatusCode::BAD_REQUEST => {
-                return Err(RequestErrors::BadRequest(
-                    res.text().await.map_err(|e| RequestErrors::from(e))?,
-                ))
-            }
-            reqwest::StatusCode::UNAUTHORIZED => {
-                return Err(RequestErrors::Unauthorized(
-                    res.text().await.map_err(|e| RequestErrors::from(e))?,
-                ))
+    }
+
+    /// Runs a call without joining identical calls in flight
+    pub(crate) async fn run_chat_completions(
+        &self,
+        mut request: RequestBody,
+    ) -> Result<ChatCompletionsResponse, RequestErrors> {
+        let mut headers = HeaderMap::new();
+        let mut pipeline = Pipeline::new(self.middleware.clone());
+        let mut result = match pipeline.request(&mut request, &mut headers) {
+            Some(result) => result,
+            None => self.execute_chat_completions(&request, &headers).await,
+        };
+        pipeline.response(&request, &mut result);
+        result
+    }
+
+    async fn execute_chat_completions(
+        &self,
+        request: &RequestBody,
+        headers: &HeaderMap,
+    ) -> Result<ChatCompletionsResponse, RequestErrors> {
+        let mut call = Call::new(self.observers.clone(), ENDPOINT, request, false);
+        #[cfg(feature = "tracing")]
+        let span = call.span().clone();
+        let result = async {
+            let res = self
+                .post_chat_completions(request, headers, &mut call)
+                .await?;
+            let body = res.bytes().await?;
+            serde_json::from_slice::<ChatCompletionsResponse>(&body)
+                .map_err(|e| RequestErrors::DecodeError(e.to_string()))
+        };
+        #[cfg(feature = "tracing")]
+        let result = tracing::Instrument::instrument(result, span);
+        let result = result.await;
+        match &result {
+            Ok(body) => call.response(Some(&body.usage)),
+            Err(e) => call.error(e),
+        }
+        result
+    }
+
+    /// Posts a request to the chat completions endpoint and maps non-200 statuses to errors
+    ///
+    /// Fails fast with [`RequestErrors::CircuitOpen`] while the client's circuit breaker
+    /// is open, and reports the outcome to it otherwise.
+    pub(crate) async fn post_chat_completions(
+        &self,
+        request: &RequestBody,
+        headers: &HeaderMap,
+        call: &mut Call,
+    ) -> Result<HttpResponse, RequestErrors> {
+        let Some(breaker) = &self.breaker else {
+            return self.post_with_key_failover(request, headers, call).await;
+        };
+        if !breaker.allow() {
+            return Err(RequestErrors::CircuitOpen(self.url.clone()));
+        }
+        let result = self.post_with_key_failover(request, headers, call).await;
+        breaker.record(&result);
+        result
+    }
+
+    /// Failures caused by the key itself are reported to the key provider, and the request
+    /// is retried as long as the provider hands out a key that hasn't been tried yet.
+    async fn post_with_key_failover(
+        &self,
+        request: &RequestBody,
+        headers: &HeaderMap,
+        call: &mut Call,
+    ) -> Result<HttpResponse, RequestErrors> {
+        let provider = self.key_provider();
+        let mut tried = Vec::new();
+        loop {
+            let key = match provider.key() {
+                Ok(key) if !tried.iter().any(|(k, _)| *k == key) => key,
+                // The provider has nothing new to offer; surface the last key failure
+                result => {
+                    return Err(tried
+                        .pop()
+                        .map(|(_, e)| e)
+                        .unwrap_or_else(|| result.err().unwrap_or(RequestErrors::Unknown)))
+                }
+            };
+            call.attempt();
+            match self
+                .send_chat_completions(request, headers, &key, call)
+                .await
+            {
+                Ok(res) => {
+                    provider.report_success(&key);
+                    return Ok(res);
+                }
+                Err(e) => {
+                    provider.report_error(&key, &e);
+                    if !is_key_error(&e) {
+                        return Err(e);
+                    }
+                    tried.push((key, e));
+                }
             }
-            reqwest::StatusCode::FORBIDDEN => return Err(RequestErrors::Forbidden),
-            reqwest::StatusCode::TOO_MANY_REQUESTS => {
-                return Err(RequestErrors::RateLimitExceeded(
-                    res.text().await.map_err(|e| RequestErrors::from(e))?,
-                ))
+        }
+    }
+
+    /// Sends one attempt, recording the response status on `call` whatever it is
+    async fn send_chat_completions(
+        &self,
+        request: &RequestBody,
+        extra_headers: &HeaderMap,
+        api_key: &ApiKey,
+        call: &mut Call,
+    ) -> Result<HttpResponse, RequestErrors> {
+        let mut headers = self.default_headers(api_key)?;
+        headers.extend(extra_headers.clone());
+        let body = self.profile.encode(request)?;
+        let res = self
+            .transport
+            .send(HttpRequest {
+                method: Method::POST,
+                url: format!("{}/{}", self.url, self.profile.path),
+                headers,
+                body,
+                timeout: self.timeout,
+            })
+            .await?;
+        call.status(res.status.as_u16());
+        match res.status {
+            StatusCode::OK => Ok(res),
+            StatusCode::BAD_REQUEST => Err(RequestErrors::BadRequest(res.text().await?)),
+            StatusCode::UNAUTHORIZED => Err(RequestErrors::Unauthorized(res.text().await?)),
+            StatusCode::PAYMENT_REQUIRED => {
+                Err(RequestErrors::InsufficientBalance(res.text().await?))
             }
-            _ => {
-                return Err(RequestErrors::StatusError(
-                    res.status(),
-                    res.text().await.map_err(|e| RequestErrors::from(e))?,
-                ))
+            StatusCode::FORBIDDEN => Err(RequestErrors::Forbidden),
+            StatusCode::TOO_MANY_REQUESTS => {
+                Err(RequestErrors::RateLimitExceeded(res.text().await?))
             }
+            status => Err(RequestErrors::StatusError(status, res.text().await?)),
         }
-        let body: ChatCompletionsResponse = res.json().await.map_err(|e| RequestErrors::from(e))?;
-        Ok(body)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{sync::Arc, time::Duration};
+
     use super::*;
     use crate::{
-        client::chat_completions::request::{Message, Model, Temperature},
+        client::{
+            breaker::{BreakerState, CircuitBreaker},
+            chat_completions::request::{Message, Model, Temperature},
+            keys::KeyPool,
+            transport::MemoryTransport,
+        },
         request::ResponseFormat,
+        testing::response_json,
     };
 
+    fn hello() -> RequestBody {
+        RequestBody::new_messages(vec![Message::new_user_message("Hello".to_string())])
+    }
+
+    #[tokio::test]
+    async fn test_chat_completions_over_transport() {
+        let transport = Arc::new(MemoryTransport::new());
+        transport.push_json(&response_json("Hi"));
+        let client = DeepSeekClient::new_with_url_and_api_key(
+            "http://localhost".to_string(),
+            "key".to_string(),
+        )
+        .with_transport(transport.clone());
+        let response = client.chat_completions(hello()).await.unwrap();
+        assert_eq!(response.choices[0].message.content.as_deref(), Some("Hi"));
+
+        let sent = &transport.requests()[0];
+        assert_eq!(sent.method, Method::POST);
+        assert_eq!(sent.url, "http://localhost/chat/completions");
+        assert_eq!(sent.headers[http::header::AUTHORIZATION], "Bearer key");
+        let body: RequestBody = serde_json::from_slice(&sent.body).unwrap();
+        assert_eq!(body, hello());
+    }
+
+    #[tokio::test]
+    async fn test_status_errors() {
+        let transport = Arc::new(MemoryTransport::new());
+        transport.push_response(StatusCode::PAYMENT_REQUIRED, "no credit");
+        transport.push_response(StatusCode::SERVICE_UNAVAILABLE, "busy");
+        transport.push_response(StatusCode::OK, "not json");
+        let client = DeepSeekClient::new_with_api_key("key".to_string()).with_transport(transport);
+        assert!(matches!(
+            client.chat_completions(hello()).await,
+            Err(RequestErrors::InsufficientBalance(body)) if body == "no credit"
+        ));
+        assert!(matches!(
+            client.chat_completions(hello()).await,
+            Err(RequestErrors::StatusError(
+                StatusCode::SERVICE_UNAVAILABLE,
+                _
+            ))
+        ));
+        assert!(matches!(
+            client.chat_completions(hello()).await,
+            Err(RequestErrors::DecodeError(_))
+        ));
+        assert!(matches!(
+            client.chat_completions(hello()).await,
+            Err(RequestErrors::ConnectionError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_status_reported_on_errors() {
+        use crate::client::observer::{Observer, RequestInfo};
+
+        #[derive(Clone, Default)]
+        struct Statuses(Arc<std::sync::Mutex<Vec<Option<u16>>>>);
+
+        impl Observer for Statuses {
+            fn on_error(&self, request: &RequestInfo, _error: &RequestErrors, _elapsed: Duration) {
+                self.0.lock().unwrap().push(request.status);
+            }
+        }
+
+        let transport = Arc::new(MemoryTransport::new());
+        transport.push_response(StatusCode::TOO_MANY_REQUESTS, "slow down");
+        let statuses = Statuses::default();
+        let client = DeepSeekClient::new_with_api_key("key".to_string())
+            .with_transport(transport)
+            .with_observer(statuses.clone());
+        assert!(client.chat_completions(hello()).await.is_err());
+        // The second call finds no response queued and fails before any status
+        assert!(client.chat_completions(hello()).await.is_err());
+        assert_eq!(*statuses.0.lock().unwrap(), [Some(429), None]);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        let transport = Arc::new(MemoryTransport::new());
+        transport.push_response(StatusCode::SERVICE_UNAVAILABLE, "busy");
+        transport.push_response(StatusCode::BAD_REQUEST, "bad");
+        let client = DeepSeekClient::new_with_api_key("key".to_string())
+            .with_transport(transport.clone())
+            .with_circuit_breaker(CircuitBreaker::new(2, Duration::from_millis(50)));
+        assert_eq!(client.circuit_state(), Some(BreakerState::Closed));
+        // A bad request says nothing about the backend and leaves the count alone
+        assert!(client.chat_completions(hello()).await.is_err());
+        assert!(client.chat_completions(hello()).await.is_err());
+        assert_eq!(client.circuit_state(), Some(BreakerState::Closed));
+        // The transport is empty now, so the call fails to connect
+        assert!(client.chat_completions(hello()).await.is_err());
+        assert_eq!(client.circuit_state(), Some(BreakerState::Open));
+        assert!(matches!(
+            client.chat_completions(hello()).await,
+            Err(RequestErrors::CircuitOpen(_))
+        ));
+        assert_eq!(transport.requests().len(), 3);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        transport.push_json(&response_json("Hi"));
+        assert!(client.chat_completions(hello()).await.is_ok());
+        assert_eq!(client.circuit_state(), Some(BreakerState::Closed));
+    }
+
+    #[tokio::test]
+    async fn test_key_failover() {
+        let transport = Arc::new(MemoryTransport::new());
+        transport.push_response(StatusCode::UNAUTHORIZED, "revoked");
+        transport.push_json(&response_json("Hi"));
+        let client = DeepSeekClient::new_with_key_provider(KeyPool::new(vec![
+            "key-a".to_string(),
+            "key-b".to_string(),
+        ]))
+        .with_transport(transport.clone());
+        assert!(client.chat_completions(hello()).await.is_ok());
+        let keys: Vec<_> = transport
+            .requests()
+            .iter()
+            .map(|r| r.headers[http::header::AUTHORIZATION].clone())
+            .collect();
+        assert_eq!(keys, ["Bearer key-a", "Bearer key-b"]);
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_chat_completions() {
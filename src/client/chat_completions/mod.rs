@@ -1,3 +1,5 @@
+#[allow(clippy::module_inception)]
 pub mod chat_completions;
-pub mod request;
-pub mod response;
+pub mod stream;
+
+pub use crate::types::{request, response};
@@ -0,0 +1,873 @@
+//! Streaming chat completions over server-sent events
+
+use std::{
+    collections::BTreeMap,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{
+    future::{BoxFuture, Either},
+    FutureExt, Stream, StreamExt,
+};
+use futures_timer::Delay;
+use http::HeaderMap;
+pub use tokio_util::sync::CancellationToken;
+
+use super::{
+    chat_completions::ENDPOINT,
+    request::RequestBody,
+    request::Role,
+    response::{
+        ChatCompletionsChoices, ChatCompletionsChunk, ChatCompletionsResponse, FinishReasons,
+        FunctionCall, Message, ToolsCall, Usage,
+    },
+};
+use crate::{
+    client::{client::DeepSeekClient, middleware::Pipeline, observer::Call},
+    errors::request_errors::{InterruptReason, RequestErrors},
+};
+
+/// A stream of chat completion chunks, ending after the server sends `[DONE]`
+pub type ChatCompletionsStream =
+    Pin<Box<dyn Stream<Item = Result<ChatCompletionsChunk, RequestErrors>> + Send>>;
+
+impl DeepSeekClient {
+    /// Sends a chat completion request with streaming enabled and returns the chunk stream
+    ///
+    /// # Example
+    /// ```no_run
+    /// use clia_deepseek_rs::{DeepSeekClient, client::chat_completions::request::{RequestBody, Message}};
+    /// use futures::StreamExt;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let client = DeepSeekClient::default().unwrap();
+    /// let request = RequestBody::new_messages(
+    ///     vec![Message::new_user_message("Hello".to_string())]
+    /// );
+    /// let mut stream = client.chat_completions_stream(request).await.unwrap();
+    /// while let Some(chunk) = stream.next().await {
+    ///     print!("{}", chunk.unwrap().choices[0].delta.content.clone().unwrap_or_default());
+    /// }
+    /// # }
+    /// ```
+    pub async fn chat_completions_stream(
+        &self,
+        request: RequestBody,
+    ) -> Result<ChatCompletionsStream, RequestErrors> {
+        self.start_chat_completions_stream(request, None).await
+    }
+
+    /// Like [`Self::chat_completions_stream`], but stops when `cancel` fires
+    ///
+    /// Cancelling closes the connection and ends the stream with
+    /// [`RequestErrors::StreamInterrupted`] carrying the text received so far.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use clia_deepseek_rs::{
+    ///     client::chat_completions::{request::{Message, RequestBody}, stream::CancellationToken},
+    ///     errors::request_errors::RequestErrors,
+    ///     DeepSeekClient,
+    /// };
+    /// use futures::StreamExt;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let client = DeepSeekClient::default().unwrap();
+    /// let request = RequestBody::new_messages(vec![Message::new_user_message("Hello".to_string())]);
+    /// let cancel = CancellationToken::new();
+    /// let mut stream = client
+    ///     .chat_completions_stream_with_cancellation(request, cancel.clone())
+    ///     .await
+    ///     .unwrap();
+    /// tokio::spawn(async move {
+    ///     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    ///     cancel.cancel();
+    /// });
+    /// while let Some(chunk) = stream.next().await {
+    ///     if let Err(RequestErrors::StreamInterrupted { content, .. }) = chunk {
+    ///         println!("stopped after: {}", content);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn chat_completions_stream_with_cancellation(
+        &self,
+        request: RequestBody,
+        cancel: CancellationToken,
+    ) -> Result<ChatCompletionsStream, RequestErrors> {
+        self.start_chat_completions_stream(request, Some(cancel))
+            .await
+    }
+
+    async fn start_chat_completions_stream(
+        &self,
+        request: RequestBody,
+        cancel: Option<CancellationToken>,
+    ) -> Result<ChatCompletionsStream, RequestErrors> {
+        let mut request = request.with_stream(true);
+        let mut headers = HeaderMap::new();
+        let mut pipeline = Pipeline::new(self.middleware.clone());
+        let started = match pipeline.request(&mut request, &mut headers) {
+            Some(result) => result.map(replay),
+            None => {
+                self.execute_chat_completions_stream(&request, &headers, cancel)
+                    .await
+            }
+        };
+        let stream = match started {
+            Ok(stream) => stream,
+            Err(e) => {
+                let mut result = Err(e);
+                pipeline.response(&request, &mut result);
+                replay(result?)
+            }
+        };
+        Ok(Box::pin(stream.map(move |mut chunk| {
+            pipeline.chunk(&request, &mut chunk);
+            chunk
+        })))
+    }
+
+    async fn execute_chat_completions_stream(
+        &self,
+        request: &RequestBody,
+        headers: &HeaderMap,
+        cancel: Option<CancellationToken>,
+    ) -> Result<ChatCompletionsStream, RequestErrors> {
+        let mut call = Call::new(self.observers.clone(), ENDPOINT, request, true);
+        #[cfg(feature = "tracing")]
+        let span = call.span().clone();
+        let res = self.post_chat_completions(request, headers, &mut call);
+        #[cfg(feature = "tracing")]
+        let res = tracing::Instrument::instrument(res, span);
+        let res = match &cancel {
+            Some(cancel) => {
+                let cancelled = std::pin::pin!(cancel.cancelled());
+                match futures::future::select(cancelled, std::pin::pin!(res)).await {
+                    Either::Left(_) => {
+                        Err(RequestErrors::interrupted(InterruptReason::Cancelled, true))
+                    }
+                    Either::Right((res, _)) => res,
+                }
+            }
+            None => res.await,
+        };
+        match res {
+            Ok(res) => Ok(Box::pin(ObservedStream {
+                inner: Box::pin(InterruptibleStream::new(
+                    decode_chunks(res.body),
+                    self.stream_idle_timeout,
+                    cancel,
+                )),
+                call,
+                finished: false,
+            })),
+            Err(e) => {
+                call.error(&e);
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Reports chunks, the end of the stream and stream errors to the call's observers
+///
+/// A chunk that fails to decode is passed on without ending the stream; the call is
+/// reported once the stream really ends.
+struct ObservedStream {
+    inner: ChatCompletionsStream,
+    call: Call,
+    finished: bool,
+}
+
+impl Stream for ObservedStream {
+    type Item = Result<ChatCompletionsChunk, RequestErrors>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        if this.finished {
+            return Poll::Ready(None);
+        }
+        let item = futures::ready!(this.inner.poll_next_unpin(cx));
+        match &item {
+            Some(Ok(chunk)) => this.call.chunk(chunk),
+            Some(Err(RequestErrors::DecodeError(_))) => {}
+            Some(Err(e)) => {
+                this.finished = true;
+                this.call.error(e);
+            }
+            None => {
+                this.finished = true;
+                this.call.response(None);
+            }
+        }
+        Poll::Ready(item)
+    }
+}
+
+/// Ends a stream on cancellation or when it goes idle, attaching the partial text to
+/// [`RequestErrors::StreamInterrupted`]
+struct InterruptibleStream {
+    inner: ChatCompletionsStream,
+    received: StreamAccumulator,
+    /// Whether a chunk has arrived
+    started: bool,
+    idle_timeout: Option<Duration>,
+    idle: Option<Delay>,
+    cancelled: Option<BoxFuture<'static, ()>>,
+    finished: bool,
+}
+
+impl InterruptibleStream {
+    fn new(
+        inner: ChatCompletionsStream,
+        idle_timeout: Option<Duration>,
+        cancel: Option<CancellationToken>,
+    ) -> Self {
+        InterruptibleStream {
+            inner,
+            received: StreamAccumulator::new(),
+            started: false,
+            idle_timeout,
+            idle: idle_timeout.map(Delay::new),
+            cancelled: cancel
+                .map(|cancel| -> BoxFuture<'static, ()> { Box::pin(cancel.cancelled_owned()) }),
+            finished: false,
+        }
+    }
+
+    /// Ends the stream on [`RequestErrors::StreamInterrupted`], filling in the text
+    /// received so far
+    ///
+    /// Transport errors after the first chunk, such as a reset connection, become
+    /// [`InterruptReason::ConnectionClosed`] so the partial text isn't lost. Errors
+    /// decoding a single chunk, and errors before the first one, pass through.
+    fn interrupt(&mut self, error: RequestErrors) -> RequestErrors {
+        let error = match error {
+            RequestErrors::StreamInterrupted { .. } | RequestErrors::DecodeError(_) => error,
+            _ if self.started => {
+                RequestErrors::interrupted(InterruptReason::ConnectionClosed, false)
+            }
+            _ => error,
+        };
+        match error {
+            RequestErrors::StreamInterrupted {
+                reason,
+                closed_cleanly,
+                ..
+            } => {
+                self.finished = true;
+                RequestErrors::StreamInterrupted {
+                    reason,
+                    content: self.received.content().to_string(),
+                    reasoning_content: self.received.reasoning_content().to_string(),
+                    closed_cleanly,
+                }
+            }
+            error => error,
+        }
+    }
+}
+
+impl Stream for InterruptibleStream {
+    type Item = Result<ChatCompletionsChunk, RequestErrors>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        if this.finished {
+            return Poll::Ready(None);
+        }
+        if let Some(cancelled) = &mut this.cancelled {
+            if cancelled.poll_unpin(cx).is_ready() {
+                let error = RequestErrors::interrupted(InterruptReason::Cancelled, true);
+                return Poll::Ready(Some(Err(this.interrupt(error))));
+            }
+        }
+        match this.inner.poll_next_unpin(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                this.received.push(&chunk);
+                this.started = true;
+                if let (Some(idle), Some(timeout)) = (&mut this.idle, this.idle_timeout) {
+                    idle.reset(timeout);
+                }
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(this.interrupt(e)))),
+            Poll::Ready(None) => {
+                this.finished = true;
+                Poll::Ready(None)
+            }
+            Poll::Pending => {
+                let timed_out = this
+                    .idle
+                    .as_mut()
+                    .is_some_and(|idle| idle.poll_unpin(cx).is_ready());
+                if !timed_out {
+                    return Poll::Pending;
+                }
+                let error = RequestErrors::interrupted(InterruptReason::IdleTimeout, false);
+                Poll::Ready(Some(Err(this.interrupt(error))))
+            }
+        }
+    }
+}
+
+/// Folds streamed chunks back into the response a non-streaming call returns
+///
+/// Content and reasoning are concatenated per choice, and tool call fragments are
+/// merged by their `index`. The `Usage` of the last chunk that carries one is kept.
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::client::chat_completions::{
+///     response::{ChatCompletionsChunk, ChunkChoice, Delta, FinishReasons},
+///     stream::StreamAccumulator,
+/// };
+///
+/// let chunk = |content: &str, finish_reason| ChatCompletionsChunk {
+///     id: "1".to_string(),
+///     choices: vec![ChunkChoice {
+///         delta: Delta { content: Some(content.to_string()), ..Delta::default() },
+///         finish_reason,
+///         index: 0,
+///     }],
+///     created: 0,
+///     model: "deepseek-chat".to_string(),
+///     object: "chat.completion.chunk".to_string(),
+///     usage: None,
+/// };
+/// let mut accumulator = StreamAccumulator::new();
+/// accumulator.push(&chunk("Hel", None));
+/// accumulator.push(&chunk("lo", Some(FinishReasons::Stop)));
+/// assert_eq!(accumulator.content(), "Hello");
+/// let response = accumulator.finish().unwrap();
+/// assert_eq!(response.choices[0].message.content.as_deref(), Some("Hello"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StreamAccumulator {
+    id: String,
+    created: i32,
+    model: String,
+    choices: BTreeMap<i32, ChoiceState>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ChoiceState {
+    role: Option<Role>,
+    content: Option<String>,
+    reasoning_content: Option<String>,
+    tool_calls: BTreeMap<i32, ToolCallState>,
+    finish_reason: Option<FinishReasons>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ToolCallState {
+    id: String,
+    type_: String,
+    name: String,
+    arguments: String,
+}
+
+impl StreamAccumulator {
+    pub fn new() -> Self {
+        StreamAccumulator::default()
+    }
+
+    /// Drains `stream` and returns the rebuilt response, or the first error
+    pub async fn collect(
+        mut stream: ChatCompletionsStream,
+    ) -> Result<ChatCompletionsResponse, RequestErrors> {
+        let mut accumulator = StreamAccumulator::new();
+        while let Some(chunk) = stream.next().await {
+            accumulator.push(&chunk?);
+        }
+        accumulator.finish()
+    }
+
+    /// Adds a chunk
+    pub fn push(&mut self, chunk: &ChatCompletionsChunk) {
+        if self.id.is_empty() {
+            self.id = chunk.id.clone();
+            self.created = chunk.created;
+            self.model = chunk.model.clone();
+        }
+        if chunk.usage.is_some() {
+            self.usage = chunk.usage.clone();
+        }
+        for choice in &chunk.choices {
+            let state = self.choices.entry(choice.index).or_default();
+            let delta = &choice.delta;
+            if delta.role.is_some() {
+                state.role = delta.role.clone();
+            }
+            append(&mut state.content, &delta.content);
+            append(&mut state.reasoning_content, &delta.reasoning_content);
+            for call in delta.tool_calls.iter().flatten() {
+                let call_state = state.tool_calls.entry(call.index).or_default();
+                // Some servers repeat these in every fragment; only arguments are split
+                set_once(&mut call_state.id, &call.id);
+                set_once(&mut call_state.type_, &call.type_);
+                if let Some(function) = &call.function_call {
+                    set_once(&mut call_state.name, &function.name);
+                    call_state
+                        .arguments
+                        .push_str(function.arguments.as_deref().unwrap_or_default());
+                }
+            }
+            if choice.finish_reason.is_some() {
+                state.finish_reason = choice.finish_reason.clone();
+            }
+        }
+    }
+
+    /// Returns the content of the first choice received so far
+    pub fn content(&self) -> &str {
+        self.choices
+            .values()
+            .next()
+            .and_then(|choice| choice.content.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Returns the reasoning of the first choice received so far
+    pub fn reasoning_content(&self) -> &str {
+        self.choices
+            .values()
+            .next()
+            .and_then(|choice| choice.reasoning_content.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Returns the last `Usage` received, if any
+    pub fn usage(&self) -> Option<&Usage> {
+        self.usage.as_ref()
+    }
+
+    /// Builds the response
+    ///
+    /// Fails with [`RequestErrors::DecodeError`] if no chunk arrived or a choice never
+    /// received a finish reason, i.e. the stream was cut short. A stream without usage
+    /// reports zero tokens.
+    pub fn finish(self) -> Result<ChatCompletionsResponse, RequestErrors> {
+        if self.choices.is_empty() {
+            return Err(RequestErrors::DecodeError(
+                "stream ended without any choices".to_string(),
+            ));
+        }
+        let choices = self
+            .choices
+            .into_iter()
+            .map(|(index, choice)| {
+                let finish_reason = choice.finish_reason.ok_or_else(|| {
+                    RequestErrors::DecodeError(format!(
+                        "stream ended before choice {} finished",
+                        index
+                    ))
+                })?;
+                let tool_calls = (!choice.tool_calls.is_empty()).then(|| {
+                    choice
+                        .tool_calls
+                        .into_values()
+                        .map(|call| ToolsCall {
+                            id: call.id,
+                            type_: call.type_,
+                            function_call: FunctionCall {
+                                name: call.name,
+                                arguments: call.arguments,
+                            },
+                        })
+                        .collect()
+                });
+                Ok(ChatCompletionsChoices {
+                    finish_reason,
+                    index,
+                    message: Message {
+                        content: choice.content,
+                        reasoning_content: choice.reasoning_content,
+                        role: choice.role.unwrap_or(Role::Assistant),
+                        tool_calls,
+                    },
+                })
+            })
+            .collect::<Result<_, RequestErrors>>()?;
+        Ok(ChatCompletionsResponse {
+            id: self.id,
+            choices,
+            created: self.created,
+            model: self.model,
+            object: "chat.completion".to_string(),
+            usage: self.usage.unwrap_or_default(),
+            cache_hit: false,
+        })
+    }
+}
+
+fn append(target: &mut Option<String>, fragment: &Option<String>) {
+    if let Some(fragment) = fragment {
+        target.get_or_insert_with(String::new).push_str(fragment);
+    }
+}
+
+fn set_once(field: &mut String, value: &Option<String>) {
+    if let Some(value) = value.as_deref().filter(|_| field.is_empty()) {
+        field.push_str(value);
+    }
+}
+
+/// Replays a full response as a single-chunk stream
+fn replay(response: ChatCompletionsResponse) -> ChatCompletionsStream {
+    Box::pin(futures::stream::once(futures::future::ready(Ok(
+        response.into()
+    ))))
+}
+
+/// Turns a raw SSE byte stream into parsed chunks
+pub(crate) fn decode_chunks<S, B, E>(bytes: S) -> ChatCompletionsStream
+where
+    S: Stream<Item = Result<B, E>> + Send + 'static,
+    B: AsRef<[u8]>,
+    RequestErrors: From<E>,
+{
+    let state = (Box::pin(bytes), SseDecoder::default(), false);
+    Box::pin(futures::stream::unfold(
+        state,
+        |(mut bytes, mut decoder, mut done)| async move {
+            loop {
+                if done {
+                    return None;
+                }
+                if let Some(data) = decoder.next_event() {
+                    if data == "[DONE]" {
+                        return None;
+                    }
+                    let chunk = serde_json::from_str::<ChatCompletionsChunk>(&data)
+                        .map_err(|e| RequestErrors::DecodeError(e.to_string()));
+                    return Some((chunk, (bytes, decoder, done)));
+                }
+                match bytes.next().await {
+                    Some(Ok(b)) => decoder.push(b.as_ref()),
+                    Some(Err(e)) => {
+                        done = true;
+                        return Some((Err(RequestErrors::from(e)), (bytes, decoder, done)));
+                    }
+                    None => {
+                        done = true;
+                        let error =
+                            RequestErrors::interrupted(InterruptReason::ConnectionClosed, true);
+                        return Some((Err(error), (bytes, decoder, done)));
+                    }
+                }
+            }
+        },
+    ))
+}
+
+/// Incremental parser for `text/event-stream` bodies
+#[derive(Debug, Default)]
+pub(crate) struct SseDecoder {
+    buffer: Vec<u8>,
+}
+
+impl SseDecoder {
+    pub(crate) fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Returns the `data` payload of the next complete event, skipping comments
+    /// and events without data
+    pub(crate) fn next_event(&mut self) -> Option<String> {
+        loop {
+            let (end, separator_len) = find_event_end(&self.buffer)?;
+            let event: Vec<u8> = self.buffer.drain(..end + separator_len).collect();
+            let event = String::from_utf8_lossy(&event[..end]);
+            let data: Vec<&str> = event
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|data| data.strip_prefix(' ').unwrap_or(data))
+                .collect();
+            if !data.is_empty() {
+                return Some(data.join("\n"));
+            }
+        }
+    }
+}
+
+fn find_event_end(buffer: &[u8]) -> Option<(usize, usize)> {
+    let lf = buffer.windows(2).position(|w| w == b"\n\n").map(|i| (i, 2));
+    let crlf = buffer
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .map(|i| (i, 4));
+    match (lf, crlf) {
+        (Some(a), Some(b)) => Some(if a.0 <= b.0 { a } else { b }),
+        (a, b) => a.or(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHUNK: &str = r#"{"id":"1","object":"chat.completion.chunk","created":1,"model":"deepseek-chat","choices":[{"index":0,"delta":{"content":"Hi"},"finish_reason":null}]}"#;
+
+    #[test]
+    fn test_sse_decoder_split_events() {
+        let mut decoder = SseDecoder::default();
+        decoder.push(b": keep-alive\n\ndata: {\"a\"");
+        assert_eq!(decoder.next_event(), None);
+        decoder.push(b":1}\n\ndata: [DONE]\r\n\r\n");
+        assert_eq!(decoder.next_event(), Some("{\"a\":1}".to_string()));
+        assert_eq!(decoder.next_event(), Some("[DONE]".to_string()));
+        assert_eq!(decoder.next_event(), None);
+    }
+
+    #[tokio::test]
+    async fn test_decode_chunks() {
+        let body = format!("data: {CHUNK}\n\ndata: {CHUNK}\n\ndata: [DONE]\n\n");
+        let (first, second) = body.as_bytes().split_at(40);
+        let bytes = futures::stream::iter(vec![
+            Ok::<_, RequestErrors>(first.to_vec()),
+            Ok(second.to_vec()),
+        ]);
+        let chunks: Vec<_> = decode_chunks(bytes).collect().await;
+        assert_eq!(chunks.len(), 2);
+        let chunk = chunks[0].as_ref().unwrap();
+        assert_eq!(chunk.choices[0].delta.content.as_deref(), Some("Hi"));
+        assert!(chunk.usage.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_accumulate_tool_calls() {
+        let body = [
+            r#"{"id":"2","object":"chat.completion.chunk","created":1,"model":"deepseek-chat","choices":[{"index":0,"delta":{"role":"assistant","content":"","tool_calls":[{"index":0,"id":"call_0","type":"function","function":{"name":"weather","arguments":""}}]},"finish_reason":null}]}"#,
+            r#"{"id":"2","object":"chat.completion.chunk","created":1,"model":"deepseek-chat","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"city\":"}},{"index":1,"id":"call_1","type":"function","function":{"name":"time","arguments":"{}"}}]},"finish_reason":null}]}"#,
+            r#"{"id":"2","object":"chat.completion.chunk","created":1,"model":"deepseek-chat","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"Paris\"}"}}]},"finish_reason":"tool_calls"}]}"#,
+            r#"{"id":"2","object":"chat.completion.chunk","created":1,"model":"deepseek-chat","choices":[],"usage":{"completion_tokens":9,"prompt_tokens":3,"prompt_cache_hit_tokens":0,"prompt_cache_miss_tokens":3,"total_tokens":12}}"#,
+        ]
+        .iter()
+        .map(|chunk| format!("data: {chunk}\n\n"))
+        .collect::<String>()
+            + "data: [DONE]\n\n";
+        let bytes = futures::stream::iter(vec![Ok::<_, RequestErrors>(body.into_bytes())]);
+        let response = StreamAccumulator::collect(decode_chunks(bytes))
+            .await
+            .unwrap();
+        let choice = &response.choices[0];
+        assert_eq!(choice.finish_reason, FinishReasons::ToolCalls);
+        let calls = choice.message.tool_calls.as_ref().unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].id, "call_0");
+        assert_eq!(calls[0].function_call.arguments, r#"{"city":"Paris"}"#);
+        assert_eq!(calls[1].function_call.name, "time");
+        assert_eq!(response.usage.total_tokens, 12);
+    }
+
+    #[test]
+    fn test_accumulate_repeated_tool_call_fields() {
+        let mut accumulator = StreamAccumulator::new();
+        for (arguments, finish_reason) in
+            [(r#"{\"q\":"#, "null"), (r#"\"rust\"}"#, "\"tool_calls\"")]
+        {
+            let chunk = format!(
+                r#"{{"id":"3","object":"chat.completion.chunk","created":1,"model":"deepseek-chat","choices":[{{"index":0,"delta":{{"tool_calls":[{{"index":0,"id":"call_0","type":"function","function":{{"name":"search","arguments":"{arguments}"}}}}]}},"finish_reason":{finish_reason}}}]}}"#
+            );
+            accumulator.push(&serde_json::from_str(&chunk).unwrap());
+        }
+        let response = accumulator.finish().unwrap();
+        let call = &response.choices[0].message.tool_calls.as_ref().unwrap()[0];
+        assert_eq!(call.id, "call_0");
+        assert_eq!(call.type_, "function");
+        assert_eq!(call.function_call.name, "search");
+        assert_eq!(call.function_call.arguments, r#"{"q":"rust"}"#);
+    }
+
+    #[test]
+    fn test_accumulate_round_trip() {
+        let response = ChatCompletionsResponse {
+            id: "1".to_string(),
+            choices: vec![ChatCompletionsChoices {
+                finish_reason: FinishReasons::Stop,
+                index: 0,
+                message: Message {
+                    content: Some("Hi".to_string()),
+                    reasoning_content: Some("greet".to_string()),
+                    role: Role::Assistant,
+                    tool_calls: None,
+                },
+            }],
+            created: 1,
+            model: "deepseek-reasoner".to_string(),
+            object: "chat.completion".to_string(),
+            usage: Usage::default(),
+            cache_hit: false,
+        };
+        let mut accumulator = StreamAccumulator::new();
+        accumulator.push(&response.clone().into());
+        assert_eq!(accumulator.finish().unwrap(), response);
+
+        let mut accumulator = StreamAccumulator::new();
+        accumulator.push(&serde_json::from_str(CHUNK).unwrap());
+        assert_eq!(accumulator.content(), "Hi");
+        assert!(matches!(
+            accumulator.finish(),
+            Err(RequestErrors::DecodeError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_connection_closed_without_done() {
+        let bytes = futures::stream::iter(vec![Ok::<_, RequestErrors>(
+            format!("data: {CHUNK}\n\n").into_bytes(),
+        )]);
+        let stream = InterruptibleStream::new(decode_chunks(bytes), None, None);
+        let chunks: Vec<_> = stream.collect().await;
+        assert_eq!(chunks.len(), 2);
+        assert!(matches!(
+            &chunks[1],
+            Err(RequestErrors::StreamInterrupted {
+                reason: InterruptReason::ConnectionClosed,
+                content,
+                closed_cleanly: true,
+                ..
+            }) if content == "Hi"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_transport_error_keeps_partial_text() {
+        let bytes = futures::stream::iter(vec![
+            Ok(format!("data: {CHUNK}\n\n").into_bytes()),
+            Err(RequestErrors::ConnectionError(
+                "connection reset".to_string(),
+            )),
+        ]);
+        let stream = InterruptibleStream::new(decode_chunks(bytes), None, None);
+        let chunks: Vec<_> = stream.collect().await;
+        assert_eq!(chunks.len(), 2);
+        assert!(matches!(
+            &chunks[1],
+            Err(RequestErrors::StreamInterrupted {
+                reason: InterruptReason::ConnectionClosed,
+                content,
+                closed_cleanly: false,
+                ..
+            }) if content == "Hi"
+        ));
+
+        let bytes = futures::stream::iter(vec![Err::<Vec<u8>, _>(RequestErrors::ConnectionError(
+            "refused".to_string(),
+        ))]);
+        let stream = InterruptibleStream::new(decode_chunks(bytes), None, None);
+        let chunks: Vec<_> = stream.collect().await;
+        assert!(matches!(chunks[0], Err(RequestErrors::ConnectionError(_))));
+    }
+
+    /// A body that sends one chunk and then stalls
+    fn stalled() -> ChatCompletionsStream {
+        let first = futures::stream::once(async { Ok(format!("data: {CHUNK}\n\n").into_bytes()) });
+        decode_chunks(first.chain(futures::stream::pending::<Result<Vec<u8>, RequestErrors>>()))
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout() {
+        let stream = InterruptibleStream::new(stalled(), Some(Duration::from_millis(20)), None);
+        let chunks: Vec<_> = stream.collect().await;
+        assert_eq!(chunks.len(), 2);
+        assert!(matches!(
+            &chunks[1],
+            Err(RequestErrors::StreamInterrupted {
+                reason: InterruptReason::IdleTimeout,
+                closed_cleanly: false,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cancellation() {
+        let cancel = CancellationToken::new();
+        let mut stream = InterruptibleStream::new(stalled(), None, Some(cancel.clone()));
+        assert!(stream.next().await.unwrap().is_ok());
+        cancel.cancel();
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(RequestErrors::StreamInterrupted {
+                reason: InterruptReason::Cancelled,
+                content,
+                closed_cleanly: true,
+                ..
+            })) if content == "Hi"
+        ));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_over_transport() {
+        let transport = std::sync::Arc::new(crate::client::transport::MemoryTransport::new());
+        transport.push_response(
+            http::StatusCode::OK,
+            format!("data: {CHUNK}\n\ndata: {CHUNK}\n\ndata: [DONE]\n\n"),
+        );
+        let client =
+            DeepSeekClient::new_with_api_key("key".to_string()).with_transport(transport.clone());
+        let request = RequestBody::new_messages(vec![]);
+        let chunks: Vec<_> = client
+            .chat_completions_stream(request)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(chunks.len(), 2);
+        let sent: serde_json::Value =
+            serde_json::from_slice(&transport.requests()[0].body).unwrap();
+        assert_eq!(sent["stream"], true);
+    }
+
+    #[tokio::test]
+    async fn test_stream_continues_after_bad_chunk() {
+        use crate::client::observer::{Observer, RequestInfo, ResponseInfo};
+
+        #[derive(Clone, Default)]
+        struct Ends(std::sync::Arc<std::sync::Mutex<Vec<&'static str>>>);
+
+        impl Observer for Ends {
+            fn on_response(&self, _request: &RequestInfo, _response: &ResponseInfo) {
+                self.0.lock().unwrap().push("response");
+            }
+
+            fn on_error(&self, _request: &RequestInfo, _error: &RequestErrors, _elapsed: Duration) {
+                self.0.lock().unwrap().push("error");
+            }
+        }
+
+        let transport = std::sync::Arc::new(crate::client::transport::MemoryTransport::new());
+        transport.push_response(
+            http::StatusCode::OK,
+            format!("data: nope\n\ndata: {CHUNK}\n\ndata: {CHUNK}\n\ndata: [DONE]\n\n"),
+        );
+        let ends = Ends::default();
+        let client = DeepSeekClient::new_with_api_key("key".to_string())
+            .with_transport(transport)
+            .with_observer(ends.clone());
+        let chunks: Vec<_> = client
+            .chat_completions_stream(RequestBody::new_messages(vec![]))
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(chunks.len(), 3);
+        assert!(matches!(chunks[0], Err(RequestErrors::DecodeError(_))));
+        assert!(chunks[1..].iter().all(Result::is_ok));
+        assert_eq!(*ends.0.lock().unwrap(), ["response"]);
+    }
+
+    #[tokio::test]
+    async fn test_decode_chunks_invalid_json() {
+        let bytes = futures::stream::iter(vec![Ok::<_, RequestErrors>(b"data: nope\n\n".to_vec())]);
+        let chunks: Vec<_> = decode_chunks(bytes).collect().await;
+        assert!(matches!(chunks[0], Err(RequestErrors::DecodeError(_))));
+    }
+}
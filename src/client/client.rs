@@ -1,84 +1,253 @@
-use thiserror::Error;
+use std::{
+    fmt,
+    path::Path,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
+use super::{
+    breaker::{BreakerState, CircuitBreaker},
+    cache::ResponseCache,
+    dedupe::InFlight,
+    hedge::HedgePolicy,
+    keys::{ApiKey, KeyProvider, StaticKey},
+    middleware::Middleware,
+    observer::Observer,
+    provider::{AuthScheme, ProviderProfile},
+    transport::{default_transport, Transport},
+};
+pub use crate::errors::client_errors::ClientInitErrors;
+
+/// Client for the DeepSeek API
+///
+/// Clones are cheap and share the key provider, transport, observers and middleware,
+/// so a key set on one clone with `set_api_key` applies to all of them. The consuming
+/// `with_*` builders only change the client they are called on.
+#[derive(Clone)]
 pub struct DeepSeekClient {
     pub(crate) url: String,
-    pub(crate) api_key: String,
-    pub(crate) client: reqwest::Client,
+    pub(crate) keys: Arc<RwLock<Arc<dyn KeyProvider>>>,
+    pub(crate) transport: Arc<dyn Transport>,
+    pub(crate) observers: Arc<[Arc<dyn Observer>]>,
+    pub(crate) middleware: Arc<[Arc<dyn Middleware>]>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) stream_idle_timeout: Option<Duration>,
+    pub(crate) profile: Arc<ProviderProfile>,
+    pub(crate) breaker: Option<Arc<CircuitBreaker>>,
+    pub(crate) hedging: Option<Arc<HedgePolicy>>,
+    pub(crate) in_flight: Option<Arc<InFlight>>,
 }
-const URL: &'static str = "https://api.deepseek.com";
+pub(crate) const URL: &str = "https://api.deepseek.com";
 
 impl DeepSeekClient {
     pub fn new_with_api_key(api_key: String) -> Self {
-        DeepSeekClient {
-            url: URL.to_string(),
-            api_key,
-            client: reqwest::Client::new(),
-        }
+        Self::new_with_key_provider(StaticKey::new(api_key))
     }
     pub fn new_with_url_and_api_key(url: String, api_key: String) -> Self {
+        Self::new_with_url_and_key_provider(url, StaticKey::new(api_key))
+    }
+    /// Creates a client that asks `provider` for the API key on every request
+    pub fn new_with_key_provider(provider: impl KeyProvider + 'static) -> Self {
+        Self::new_with_url_and_key_provider(URL.to_string(), provider)
+    }
+    pub fn new_with_url_and_key_provider(
+        url: String,
+        provider: impl KeyProvider + 'static,
+    ) -> Self {
         DeepSeekClient {
             url,
-            api_key,
-            client: reqwest::Client::new(),
+            keys: Arc::new(RwLock::new(Arc::new(provider))),
+            transport: default_transport(),
+            observers: Arc::new([]),
+            middleware: Arc::new([]),
+            timeout: None,
+            stream_idle_timeout: None,
+            profile: Arc::new(ProviderProfile::deepseek()),
+            breaker: None,
+            hedging: None,
+            in_flight: None,
         }
     }
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Result<Self, ClientInitErrors> {
-        let api_key = std::env::var("DEEP_SEEK_API_KEY")?;
-        Ok(DeepSeekClient {
-            url: URL.to_string(),
-            api_key,
-            client: reqwest::Client::new(),
-        })
-    }
-    pub fn set_api_key(&mut self, api_key: String) {
-        self.api_key = api_key;
-    }
-    pub fn with_api_key(mut self, api_key: String) -> Self {
-        self.api_key = api_key;
+        Self::from_env_var("DEEP_SEEK_API_KEY")
+    }
+    /// Creates a client with the key stored in the environment variable `var`
+    pub fn from_env_var(var: &str) -> Result<Self, ClientInitErrors> {
+        let api_key = ApiKey::new(std::env::var(var)?)?;
+        Ok(Self::new_with_key_provider(StaticKey::new(api_key)))
+    }
+    /// Creates a client with `DEEP_SEEK_API_KEY` from the environment or a `.env` file
+    #[cfg(feature = "dotenv")]
+    pub fn from_dotenv() -> Result<Self, ClientInitErrors> {
+        let api_key = ApiKey::new(dotenvy::var("DEEP_SEEK_API_KEY")?)?;
+        Ok(Self::new_with_key_provider(StaticKey::new(api_key)))
+    }
+    /// Creates a client with the key stored in a file
+    pub fn from_key_file(path: impl AsRef<Path>) -> Result<Self, ClientInitErrors> {
+        let api_key = ApiKey::from_file(path)?;
+        Ok(Self::new_with_key_provider(StaticKey::new(api_key)))
+    }
+    /// Replaces the key provider with a single fixed key; safe to call on a shared client
+    pub fn set_api_key(&self, api_key: String) {
+        self.set_key_provider(StaticKey::new(api_key));
+    }
+    /// Uses a single fixed key; unlike [`Self::set_api_key`], clones made before this
+    /// call keep their key
+    pub fn with_api_key(self, api_key: String) -> Self {
+        self.with_key_provider(StaticKey::new(api_key))
+    }
+    /// Replaces the key provider; safe to call on a shared client
+    pub fn set_key_provider(&self, provider: impl KeyProvider + 'static) {
+        *self.keys.write().unwrap() = Arc::new(provider);
+    }
+    /// Uses `provider` for keys; unlike [`Self::set_key_provider`], clones made before
+    /// this call keep their provider
+    pub fn with_key_provider(mut self, provider: impl KeyProvider + 'static) -> Self {
+        self.keys = Arc::new(RwLock::new(Arc::new(provider)));
         self
     }
-    pub(crate) fn default_headers(&self) -> reqwest::header::HeaderMap {
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert(
-            reqwest::header::AUTHORIZATION,
-            reqwest::header::HeaderValue::from_str(&format!("Bearer {}", self.api_key)).unwrap(),
-        );
+    /// Adds an observer notified of every request, response, error and streamed chunk
+    pub fn with_observer(mut self, observer: impl Observer + 'static) -> Self {
+        let mut observers = self.observers.to_vec();
+        observers.push(Arc::new(observer));
+        self.observers = observers.into();
+        self
+    }
+    /// Adds a middleware layer around every chat completion call, streaming or not
+    ///
+    /// Layers see requests in the order they were added and results in reverse order.
+    /// Chat completions are the only endpoint the client calls, so no other requests
+    /// pass through middleware.
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        let mut layers = self.middleware.to_vec();
+        layers.push(Arc::new(middleware));
+        self.middleware = layers.into();
+        self
+    }
+    /// Serves repeated requests from `cache`; shorthand for adding it as middleware
+    pub fn with_cache(self, cache: ResponseCache) -> Self {
+        self.with_middleware(cache)
+    }
+    /// Sends requests through `transport` instead of the default reqwest client
+    pub fn with_transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+    /// Fails requests that take longer than `timeout` in total, including reading a
+    /// streamed body
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+    /// Interrupts streams that go `timeout` without a chunk
+    ///
+    /// Unlike [`Self::with_timeout`], this only bounds the gap between chunks, so long
+    /// generations are allowed as long as the server keeps sending.
+    pub fn with_stream_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.stream_idle_timeout = Some(timeout);
+        self
+    }
+    /// Fails requests fast with `RequestErrors::CircuitOpen` after consecutive
+    /// connection errors, timeouts or 5xx responses, until the breaker's cooldown ends
+    ///
+    /// Clones of the client share the breaker.
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = Some(Arc::new(breaker));
+        self
+    }
+    /// Returns the circuit breaker's state, if one is set
+    pub fn circuit_state(&self) -> Option<BreakerState> {
+        self.breaker.as_ref().map(|breaker| breaker.state())
+    }
+    /// Sets when `chat_completions_hedged` sends a duplicate request
+    ///
+    /// Clones of the client share the policy and the latencies it has observed.
+    pub fn with_hedging(mut self, policy: HedgePolicy) -> Self {
+        self.hedging = Some(Arc::new(policy));
+        self
+    }
+    /// Makes identical `chat_completions` requests share one call while it is in flight
+    ///
+    /// Requests are identical when their [`fingerprint`](super::dedupe::fingerprint)s
+    /// match; every caller receives a clone of the response or error. Clones of the
+    /// client share the calls in flight.
+    pub fn with_deduplication(mut self) -> Self {
+        self.in_flight = Some(Arc::default());
+        self
+    }
+    /// Adapts requests and authentication to another OpenAI-compatible server
+    pub(crate) fn with_profile(mut self, profile: ProviderProfile) -> Self {
+        self.profile = Arc::new(profile);
+        self
+    }
+    /// Returns the current key provider
+    pub fn key_provider(&self) -> Arc<dyn KeyProvider> {
+        self.keys.read().unwrap().clone()
+    }
+    pub(crate) fn default_headers(
+        &self,
+        api_key: &ApiKey,
+    ) -> Result<http::header::HeaderMap, ClientInitErrors> {
+        let mut headers = http::header::HeaderMap::new();
+        match &self.profile.auth {
+            _ if api_key.expose_secret().is_empty() => {}
+            AuthScheme::Bearer => {
+                headers.insert(http::header::AUTHORIZATION, api_key.header_value()?);
+            }
+            AuthScheme::Header(name) => {
+                headers.insert(name.clone(), api_key.header_value_with_prefix("")?);
+            }
+            AuthScheme::None => {}
+        }
         headers.insert(
-            reqwest::header::CONTENT_TYPE,
-            "application/json".parse().unwrap(),
+            http::header::CONTENT_TYPE,
+            http::header::HeaderValue::from_static("application/json"),
         );
-        headers
+        Ok(headers)
     }
 }
-#[derive(Debug, Error)]
-pub enum ClientInitErrors {
-    #[error("Error getting API key from environment variable")]
-    DeepSeekApiKeyNotSet(#[from] std::env::VarError),
-    #[error("Unknown error")]
-    Unknown,
+
+impl fmt::Debug for DeepSeekClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DeepSeekClient")
+            .field("url", &self.url)
+            .finish_non_exhaustive()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::client::keys::KeyPool;
+
+    fn api_key(client: &DeepSeekClient) -> String {
+        client
+            .key_provider()
+            .key()
+            .unwrap()
+            .expose_secret()
+            .to_string()
+    }
+
     #[test]
     fn test_new_with_api_key() {
         let client = DeepSeekClient::new_with_api_key("api_key".to_string());
-        assert_eq!(client.api_key, "api_key");
+        assert_eq!(api_key(&client), "api_key");
         assert_eq!(client.url, URL);
     }
     #[test]
     fn test_new_with_url_and_api_key() {
         let client =
             DeepSeekClient::new_with_url_and_api_key("url".to_string(), "api_key".to_string());
-        assert_eq!(client.api_key, "api_key");
+        assert_eq!(api_key(&client), "api_key");
         assert_eq!(client.url, "url");
     }
     #[test]
     fn test_default() {
         std::env::set_var("DEEP_SEEK_API_KEY", "api_key");
         let client = DeepSeekClient::default().unwrap();
-        assert_eq!(client.api_key, "api_key");
+        assert_eq!(api_key(&client), "api_key");
         assert_eq!(client.url, URL);
         std::env::remove_var("DEEP_SEEK_API_KEY");
     }
@@ -88,4 +257,60 @@ mod tests {
         std::env::remove_var("DEEP_SEEK_API_KEY");
         let _ = DeepSeekClient::default().unwrap();
     }
+    #[test]
+    fn test_from_env_var_invalid_key() {
+        std::env::set_var("DEEP_SEEK_TEST_INVALID_KEY", "bad\nkey");
+        let res = DeepSeekClient::from_env_var("DEEP_SEEK_TEST_INVALID_KEY");
+        assert!(matches!(res, Err(ClientInitErrors::InvalidApiKey)));
+        std::env::remove_var("DEEP_SEEK_TEST_INVALID_KEY");
+    }
+    #[test]
+    fn test_from_key_file() {
+        let path = std::env::temp_dir().join(format!("deepseek_client_key_{}", std::process::id()));
+        std::fs::write(&path, "file_key\n").unwrap();
+        let client = DeepSeekClient::from_key_file(&path).unwrap();
+        assert_eq!(api_key(&client), "file_key");
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            DeepSeekClient::from_key_file(&path),
+            Err(ClientInitErrors::KeyFile(_))
+        ));
+    }
+    #[test]
+    fn test_debug_redacts_key() {
+        let client = DeepSeekClient::new_with_api_key("sk-secret".to_string());
+        let headers = client
+            .default_headers(&client.key_provider().key().unwrap())
+            .unwrap();
+        assert!(headers[http::header::AUTHORIZATION].is_sensitive());
+        assert!(!format!("{:?}", client).contains("sk-secret"));
+        assert!(!format!("{:?}", headers).contains("sk-secret"));
+    }
+    #[test]
+    fn test_with_api_key_detaches_clone() {
+        let base = DeepSeekClient::new_with_api_key("a".to_string());
+        let other = base.clone().with_api_key("b".to_string());
+        assert_eq!(api_key(&base), "a");
+        assert_eq!(api_key(&other), "b");
+        let pooled = base
+            .clone()
+            .with_key_provider(StaticKey::new("c".to_string()));
+        assert_eq!(api_key(&base), "a");
+        assert_eq!(api_key(&pooled), "c");
+        // Clones that didn't detach still share set_api_key
+        let shared = base.clone();
+        base.set_api_key("d".to_string());
+        assert_eq!(api_key(&shared), "d");
+        assert_eq!(api_key(&other), "b");
+    }
+    #[test]
+    fn test_set_api_key_shared() {
+        let client = Arc::new(DeepSeekClient::new_with_key_provider(KeyPool::new(vec![
+            "a".to_string(),
+            "b".to_string(),
+        ])));
+        assert_eq!(api_key(&client), "a");
+        client.set_api_key("c".to_string());
+        assert_eq!(api_key(&client), "c");
+    }
 }
@@ -0,0 +1,262 @@
+//! Request fingerprints and single-flight deduplication of identical calls
+//!
+//! With [`DeepSeekClient::with_deduplication`](crate::DeepSeekClient::with_deduplication),
+//! identical requests made while one is already in flight wait for that call instead
+//! of sending their own, and all receive a clone of its result.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use futures::{
+    future::{BoxFuture, Shared},
+    FutureExt,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    errors::request_errors::RequestErrors,
+    types::{request::RequestBody, response::ChatCompletionsResponse},
+};
+
+/// Returns a stable fingerprint of `request`, e.g. for logging or deduplication
+///
+/// The fingerprint is the hex SHA-256 of the request's JSON with object keys sorted.
+/// Streaming settings are ignored, so a streamed and a non-streamed request with the
+/// same content share a fingerprint.
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::{client::dedupe::fingerprint, request::{Message, RequestBody}};
+///
+/// let request = RequestBody::new_messages(vec![Message::new_user_message("Hi".to_string())]);
+/// assert_eq!(fingerprint(&request), fingerprint(&request.clone()));
+/// assert_eq!(fingerprint(&request).len(), 64);
+/// ```
+pub fn fingerprint(request: &RequestBody) -> String {
+    let mut value = serde_json::to_value(request).unwrap_or_default();
+    if let Some(object) = value.as_object_mut() {
+        object.remove("stream");
+        object.remove("stream_options");
+    }
+    let digest = Sha256::digest(value.to_string().as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+type SharedCall = Shared<BoxFuture<'static, Result<ChatCompletionsResponse, RequestErrors>>>;
+/// Calls keyed by fingerprint, each with an id telling it apart from later calls
+type Calls = Mutex<HashMap<String, (u64, SharedCall)>>;
+
+/// Calls in flight, keyed by fingerprint
+#[derive(Default)]
+pub(crate) struct InFlight {
+    calls: Arc<Calls>,
+    next_id: AtomicU64,
+}
+
+impl InFlight {
+    /// Joins the call in flight under `key`, or starts one with `call`
+    ///
+    /// A call leaves the map as soon as it completes, so later requests never join a
+    /// finished call, even while some of its waiters haven't taken their result yet.
+    pub(crate) async fn run<F>(
+        &self,
+        key: String,
+        call: impl FnOnce() -> F,
+    ) -> Result<ChatCompletionsResponse, RequestErrors>
+    where
+        F: Future<Output = Result<ChatCompletionsResponse, RequestErrors>> + Send + 'static,
+    {
+        // Declared before the shared call so it is dropped after it, also on cancellation
+        let mut waiter = Waiter {
+            calls: &self.calls,
+            key: &key,
+            id: None,
+        };
+        let (id, shared) = self
+            .calls
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_insert_with(|| {
+                let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                let calls = self.calls.clone();
+                let key = key.clone();
+                let call = call();
+                let call = async move {
+                    let result = call.await;
+                    remove(&calls, &key, id, |_| true);
+                    result
+                };
+                (id, call.boxed().shared())
+            })
+            .clone();
+        waiter.id = Some(id);
+        shared.await
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.calls.lock().unwrap().len()
+    }
+}
+
+/// Removes the call with `id` under `key` if it is still there and `condition` holds
+fn remove(calls: &Calls, key: &str, id: u64, condition: impl FnOnce(&SharedCall) -> bool) {
+    let mut calls = calls.lock().unwrap();
+    if calls
+        .get(key)
+        .is_some_and(|(current, call)| *current == id && condition(call))
+    {
+        calls.remove(key);
+    }
+}
+
+/// Removes an unfinished call once its last waiter is gone, so later requests start a
+/// new one
+struct Waiter<'a> {
+    calls: &'a Calls,
+    key: &'a str,
+    id: Option<u64>,
+}
+
+impl Drop for Waiter<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            remove(self.calls, self.key, id, |call| {
+                call.strong_count() == Some(1)
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::atomic::AtomicUsize, time::Duration};
+
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{
+        client::transport::{HttpRequest, HttpResponse, MemoryTransport, Transport},
+        testing::{response, response_json},
+        types::request::{Message, StreamOptions},
+        DeepSeekClient,
+    };
+
+    #[test]
+    fn test_fingerprint() {
+        let request = RequestBody::new_messages(vec![Message::new_user_message("Hi".to_string())]);
+        let streamed = request.clone().with_stream_options(StreamOptions {
+            include_usage: true,
+        });
+        assert_eq!(fingerprint(&request), fingerprint(&streamed));
+        let other = RequestBody::new_messages(vec![Message::new_user_message("Ho".to_string())]);
+        assert_ne!(fingerprint(&request), fingerprint(&other));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_calls_share_one() {
+        let in_flight = InFlight::default();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let call = || {
+            let calls = calls.clone();
+            async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                Err(RequestErrors::StatusError(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    String::new(),
+                ))
+            }
+        };
+        let (a, b) = tokio::join!(
+            in_flight.run("k".to_string(), call),
+            in_flight.run("k".to_string(), call)
+        );
+        assert!(matches!(a, Err(RequestErrors::StatusError(..))));
+        assert!(matches!(b, Err(RequestErrors::StatusError(..))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(in_flight.len(), 0);
+
+        // Once finished, the next call starts afresh
+        let _ = in_flight.run("k".to_string(), call).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiter_cleans_up() {
+        let in_flight = InFlight::default();
+        let call = || async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Err(RequestErrors::Unknown)
+        };
+        let result = tokio::time::timeout(
+            Duration::from_millis(10),
+            in_flight.run("k".to_string(), call),
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(in_flight.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_finished_call_is_not_joined() {
+        let in_flight = InFlight::default();
+        let (finish, finished) = tokio::sync::oneshot::channel::<()>();
+        let mut first = Box::pin(in_flight.run("k".to_string(), || async {
+            finished.await.unwrap();
+            Ok(response("first"))
+        }));
+        let mut joined = Box::pin(in_flight.run("k".to_string(), || async {
+            unreachable!("joins the first call")
+        }));
+        assert!(futures::poll!(&mut first).is_pending());
+        assert!(futures::poll!(&mut joined).is_pending());
+
+        finish.send(()).unwrap();
+        assert_eq!(content(first.await), "first");
+        // `joined` hasn't taken its result yet, but the call is already gone
+        assert_eq!(in_flight.len(), 0);
+        let next = in_flight.run("k".to_string(), || async { Ok(response("second")) });
+        assert_eq!(content(next.await), "second");
+        assert_eq!(content(joined.await), "first");
+    }
+
+    fn content(result: Result<ChatCompletionsResponse, RequestErrors>) -> String {
+        result.unwrap().choices[0].message.content.clone().unwrap()
+    }
+
+    /// Answers from a [`MemoryTransport`] after a short wait, so calls overlap
+    struct Slow(Arc<MemoryTransport>);
+
+    impl Transport for Slow {
+        fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, RequestErrors>> {
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                self.0.send(request).await
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_deduplication() {
+        let transport = Arc::new(MemoryTransport::new());
+        transport.push_json(&response_json("Hi"));
+        let client = DeepSeekClient::new_with_api_key("key".to_string())
+            .with_transport(Slow(transport.clone()))
+            .with_deduplication();
+        let request = RequestBody::new_messages(vec![Message::new_user_message("Hi".to_string())]);
+        let (a, b) = tokio::join!(
+            client.chat_completions(request.clone()),
+            client.chat_completions(request)
+        );
+        assert_eq!(a.unwrap(), b.unwrap());
+        assert_eq!(transport.requests().len(), 1);
+    }
+}
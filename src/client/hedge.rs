@@ -0,0 +1,325 @@
+//! Hedged requests that trade a little extra load for a shorter latency tail
+//!
+//! With a [`HedgePolicy`] set through
+//! [`DeepSeekClient::with_hedging`](crate::DeepSeekClient::with_hedging),
+//! [`DeepSeekClient::chat_completions_hedged`] sends a duplicate request when the first
+//! one is slow and returns whichever succeeds first.
+
+use std::{
+    collections::VecDeque,
+    pin::pin,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use futures::future::{select, Either};
+use futures_timer::Delay;
+
+use super::client::DeepSeekClient;
+use crate::{
+    errors::request_errors::RequestErrors,
+    types::{
+        request::RequestBody,
+        response::{ChatCompletionsResponse, Usage},
+    },
+};
+
+/// Latencies kept for [`HedgePolicy::percentile`]
+const WINDOW: usize = 100;
+/// Latencies needed before the percentile replaces the fallback delay
+const MIN_SAMPLES: usize = 10;
+
+#[derive(Debug, Clone, Copy)]
+enum HedgeDelay {
+    Fixed(Duration),
+    Percentile { percentile: f64, fallback: Duration },
+}
+
+/// When [`DeepSeekClient::chat_completions_hedged`] sends its duplicate request
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use clia_deepseek_rs::client::hedge::HedgePolicy;
+///
+/// let policy = HedgePolicy::percentile(95.0, Duration::from_secs(2));
+/// // Until enough calls have been timed, the fallback applies
+/// assert_eq!(policy.delay(), Duration::from_secs(2));
+/// ```
+#[derive(Debug)]
+pub struct HedgePolicy {
+    delay: HedgeDelay,
+    latencies: Mutex<VecDeque<Duration>>,
+}
+
+impl HedgePolicy {
+    /// Hedges requests that haven't succeeded after `delay`
+    pub fn after(delay: Duration) -> Self {
+        HedgePolicy {
+            delay: HedgeDelay::Fixed(delay),
+            latencies: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Hedges requests slower than the given percentile (0 to 100) of the last 100
+    /// hedged calls, using `fallback` until 10 calls have been timed
+    pub fn percentile(percentile: f64, fallback: Duration) -> Self {
+        HedgePolicy {
+            delay: HedgeDelay::Percentile {
+                percentile: percentile.clamp(0.0, 100.0),
+                fallback,
+            },
+            latencies: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Returns how long the next call waits before hedging
+    pub fn delay(&self) -> Duration {
+        let (percentile, fallback) = match self.delay {
+            HedgeDelay::Fixed(delay) => return delay,
+            HedgeDelay::Percentile {
+                percentile,
+                fallback,
+            } => (percentile, fallback),
+        };
+        let mut latencies: Vec<Duration> = self.latencies.lock().unwrap().iter().copied().collect();
+        if latencies.len() < MIN_SAMPLES {
+            return fallback;
+        }
+        latencies.sort();
+        let rank = (percentile / 100.0 * latencies.len() as f64).ceil() as usize;
+        latencies[rank.clamp(1, latencies.len()) - 1]
+    }
+
+    fn record(&self, latency: Duration) {
+        let mut latencies = self.latencies.lock().unwrap();
+        if latencies.len() == WINDOW {
+            latencies.pop_front();
+        }
+        latencies.push_back(latency);
+    }
+}
+
+/// Result of [`DeepSeekClient::chat_completions_hedged`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct HedgedResponse {
+    pub response: ChatCompletionsResponse,
+    /// Whether a duplicate request was sent
+    pub hedged: bool,
+    /// Whether the duplicate request's response was returned
+    pub hedge_won: bool,
+    /// Usage of the returned response plus the prompt tokens of a cancelled duplicate,
+    /// which the API doesn't report; its completion tokens can't be known
+    pub usage: Usage,
+}
+
+impl DeepSeekClient {
+    /// Sends a chat completion request, hedged according to the client's
+    /// [`HedgePolicy`]
+    ///
+    /// If the request hasn't succeeded after the policy's delay, an identical request
+    /// is sent and the first successful response is returned; the other request is
+    /// dropped, which closes its connection. If one request fails, the other one is
+    /// awaited. Without a policy this sends a single request.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use clia_deepseek_rs::{
+    ///     client::hedge::HedgePolicy,
+    ///     request::{Message, RequestBody},
+    ///     DeepSeekClient,
+    /// };
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let client = DeepSeekClient::default()
+    ///     .unwrap()
+    ///     .with_hedging(HedgePolicy::percentile(95.0, Duration::from_secs(2)));
+    /// let request = RequestBody::new_messages(vec![Message::new_user_message("Hi".to_string())]);
+    /// let hedged = client.chat_completions_hedged(request).await.unwrap();
+    /// println!("hedge won: {}, tokens: {}", hedged.hedge_won, hedged.usage.total_tokens);
+    /// # }
+    /// ```
+    pub async fn chat_completions_hedged(
+        &self,
+        request: RequestBody,
+    ) -> Result<HedgedResponse, RequestErrors> {
+        let Some(policy) = self.hedging.clone() else {
+            let response = self.chat_completions(request).await?;
+            return Ok(HedgedResponse {
+                usage: response.usage.clone(),
+                response,
+                hedged: false,
+                hedge_won: false,
+            });
+        };
+        let started = Instant::now();
+        let primary = pin!(self.chat_completions(request.clone()));
+        let primary = match select(primary, Delay::new(policy.delay())).await {
+            Either::Left((result, _)) => {
+                let response = result?;
+                policy.record(started.elapsed());
+                return Ok(HedgedResponse {
+                    usage: response.usage.clone(),
+                    response,
+                    hedged: false,
+                    hedge_won: false,
+                });
+            }
+            Either::Right(((), primary)) => primary,
+        };
+        // The duplicate must not join the primary as an identical call in flight
+        let hedge = pin!(self.run_chat_completions(request));
+        let (result, hedge_won, cancelled) = match select(primary, hedge).await {
+            Either::Left((Ok(response), _)) => (Ok(response), false, true),
+            Either::Right((Ok(response), _)) => (Ok(response), true, true),
+            Either::Left((Err(_), hedge)) => (hedge.await, true, false),
+            Either::Right((Err(_), primary)) => (primary.await, false, false),
+        };
+        let response = result?;
+        policy.record(started.elapsed());
+        let mut usage = response.usage.clone();
+        if cancelled {
+            usage += &Usage {
+                prompt_tokens: response.usage.prompt_tokens,
+                prompt_cache_hit_tokens: response.usage.prompt_cache_hit_tokens,
+                prompt_cache_miss_tokens: response.usage.prompt_cache_miss_tokens,
+                total_tokens: response.usage.prompt_tokens,
+                ..Usage::default()
+            };
+        }
+        Ok(HedgedResponse {
+            response,
+            hedged: true,
+            hedge_won,
+            usage,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::BoxFuture;
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{
+        client::transport::{HttpRequest, HttpResponse, MemoryTransport, Transport},
+        testing::response_json,
+    };
+
+    /// Delays the responses of a [`MemoryTransport`] by a fixed amount per request
+    struct Delayed {
+        inner: MemoryTransport,
+        delays: Mutex<VecDeque<Duration>>,
+    }
+
+    impl Transport for Delayed {
+        fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, RequestErrors>> {
+            let delay = self.delays.lock().unwrap().pop_front().unwrap_or_default();
+            Box::pin(async move {
+                let response = self.inner.send(request).await;
+                tokio::time::sleep(delay).await;
+                response
+            })
+        }
+    }
+
+    fn client(delays: &[u64], responses: &[(StatusCode, &str)]) -> DeepSeekClient {
+        let inner = MemoryTransport::new();
+        for (status, content) in responses {
+            let body = match *status {
+                StatusCode::OK => response_json(content).to_string(),
+                _ => content.to_string(),
+            };
+            inner.push_response(*status, body);
+        }
+        let transport = Delayed {
+            inner,
+            delays: Mutex::new(delays.iter().map(|&ms| Duration::from_millis(ms)).collect()),
+        };
+        DeepSeekClient::new_with_api_key("key".to_string())
+            .with_transport(transport)
+            .with_hedging(HedgePolicy::after(Duration::from_millis(20)))
+    }
+
+    fn content(hedged: &HedgedResponse) -> &str {
+        hedged.response.choices[0]
+            .message
+            .content
+            .as_deref()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fast_primary_is_not_hedged() {
+        let client = client(&[0], &[(StatusCode::OK, "first")]);
+        let hedged = client
+            .chat_completions_hedged(RequestBody::default())
+            .await
+            .unwrap();
+        assert!(!hedged.hedged);
+        assert_eq!(hedged.usage, hedged.response.usage);
+    }
+
+    #[tokio::test]
+    async fn test_hedge_wins() {
+        // Responses are handed out in send order, so the slow primary gets "first"
+        let client = client(
+            &[200, 0],
+            &[(StatusCode::OK, "first"), (StatusCode::OK, "second")],
+        );
+        let hedged = client
+            .chat_completions_hedged(RequestBody::default())
+            .await
+            .unwrap();
+        assert!(hedged.hedged && hedged.hedge_won);
+        assert_eq!(content(&hedged), "second");
+        // The cancelled primary's prompt tokens are counted
+        assert_eq!(hedged.usage.prompt_tokens, 2);
+        assert_eq!(hedged.usage.total_tokens, 3);
+    }
+
+    #[tokio::test]
+    async fn test_hedge_with_deduplication() {
+        let client = client(
+            &[200, 0],
+            &[(StatusCode::OK, "first"), (StatusCode::OK, "second")],
+        )
+        .with_deduplication();
+        let hedged = client
+            .chat_completions_hedged(RequestBody::default())
+            .await
+            .unwrap();
+        assert!(hedged.hedged && hedged.hedge_won);
+        assert_eq!(content(&hedged), "second");
+    }
+
+    #[tokio::test]
+    async fn test_failed_primary_waits_for_hedge() {
+        let client = client(
+            &[30, 50],
+            &[
+                (StatusCode::SERVICE_UNAVAILABLE, "busy"),
+                (StatusCode::OK, "second"),
+            ],
+        );
+        let hedged = client
+            .chat_completions_hedged(RequestBody::default())
+            .await
+            .unwrap();
+        assert!(hedged.hedge_won);
+        assert_eq!(hedged.usage, hedged.response.usage);
+    }
+
+    #[test]
+    fn test_percentile_delay() {
+        let policy = HedgePolicy::percentile(90.0, Duration::from_secs(1));
+        for ms in 1..=9 {
+            policy.record(Duration::from_millis(ms * 10));
+        }
+        assert_eq!(policy.delay(), Duration::from_secs(1));
+        policy.record(Duration::from_millis(100));
+        assert_eq!(policy.delay(), Duration::from_millis(90));
+    }
+}
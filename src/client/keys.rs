@@ -0,0 +1,418 @@
+//! API key providers consulted by [`DeepSeekClient`](crate::DeepSeekClient) on every request
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
+
+use http::HeaderValue;
+use zeroize::Zeroize;
+
+use super::client::ClientInitErrors;
+use crate::errors::request_errors::RequestErrors;
+
+/// An API key that is redacted in `Debug` output and zeroized on drop
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::client::keys::ApiKey;
+///
+/// let key = ApiKey::new("sk-secret".to_string()).unwrap();
+/// assert_eq!(format!("{:?}", key), "ApiKey([REDACTED])");
+/// assert_eq!(key.expose_secret(), "sk-secret");
+/// assert!(ApiKey::new("sk-\nsecret".to_string()).is_err());
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct ApiKey(String);
+
+impl ApiKey {
+    /// Wraps `key`, rejecting values that can't be sent in an HTTP header
+    pub fn new(key: String) -> Result<Self, ClientInitErrors> {
+        let key = ApiKey(key);
+        key.header_value()?;
+        Ok(key)
+    }
+
+    /// Reads a key from the first line of a file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ClientInitErrors> {
+        let mut contents = std::fs::read_to_string(path)?;
+        let line = contents.lines().next().unwrap_or_default();
+        let key = ApiKey::new(line.trim().to_string());
+        contents.zeroize();
+        key
+    }
+
+    /// Returns the raw key
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+
+    /// Builds the `Authorization` header value, marked as sensitive
+    pub(crate) fn header_value(&self) -> Result<HeaderValue, ClientInitErrors> {
+        self.header_value_with_prefix("Bearer ")
+    }
+
+    /// Builds a header value of `prefix` followed by the key, marked as sensitive
+    pub(crate) fn header_value_with_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<HeaderValue, ClientInitErrors> {
+        let mut bearer = format!("{}{}", prefix, self.0);
+        let value = HeaderValue::from_str(&bearer);
+        bearer.zeroize();
+        let mut value = value.map_err(|_| ClientInitErrors::InvalidApiKey)?;
+        value.set_sensitive(true);
+        Ok(value)
+    }
+}
+
+/// Wraps a key without validating it; invalid keys fail when the request is built
+impl From<String> for ApiKey {
+    fn from(key: String) -> Self {
+        ApiKey(key)
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ApiKey([REDACTED])")
+    }
+}
+
+impl Drop for ApiKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Supplies the API key for each request and learns from request outcomes
+pub trait KeyProvider: Send + Sync {
+    /// Returns the key to use for the next request
+    fn key(&self) -> Result<ApiKey, RequestErrors>;
+
+    /// Called after a request made with `key` succeeded
+    fn report_success(&self, _key: &ApiKey) {}
+
+    /// Called after a request made with `key` failed
+    fn report_error(&self, _key: &ApiKey, _error: &RequestErrors) {}
+}
+
+/// Returns `true` for errors that mean the key itself can't be used
+pub fn is_key_error(error: &RequestErrors) -> bool {
+    matches!(
+        error,
+        RequestErrors::Unauthorized(_) | RequestErrors::InsufficientBalance(_)
+    )
+}
+
+/// A single fixed key
+#[derive(Debug, Clone)]
+pub struct StaticKey(ApiKey);
+
+impl StaticKey {
+    pub fn new(api_key: impl Into<ApiKey>) -> Self {
+        StaticKey(api_key.into())
+    }
+}
+
+impl KeyProvider for StaticKey {
+    fn key(&self) -> Result<ApiKey, RequestErrors> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Reads the key from an environment variable on every request
+#[derive(Debug, Clone)]
+pub struct EnvKey {
+    var: String,
+}
+
+impl EnvKey {
+    pub fn new(var: String) -> Self {
+        EnvKey { var }
+    }
+}
+
+impl KeyProvider for EnvKey {
+    fn key(&self) -> Result<ApiKey, RequestErrors> {
+        std::env::var(&self.var)
+            .map(ApiKey::from)
+            .map_err(|e| RequestErrors::NoApiKeyAvailable(format!("{}: {}", self.var, e)))
+    }
+}
+
+/// Reads the key from a file, re-reading it whenever the file's modification time changes
+#[derive(Debug)]
+pub struct FileKey {
+    path: PathBuf,
+    cached: Mutex<Option<(SystemTime, ApiKey)>>,
+}
+
+impl FileKey {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileKey {
+            path: path.into(),
+            cached: Mutex::new(None),
+        }
+    }
+}
+
+impl KeyProvider for FileKey {
+    fn key(&self) -> Result<ApiKey, RequestErrors> {
+        let error = |e: &dyn fmt::Display| {
+            RequestErrors::NoApiKeyAvailable(format!("{}: {}", self.path.display(), e))
+        };
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .map_err(|e| error(&e))?;
+        let mut cached = self.cached.lock().unwrap();
+        if let Some((at, key)) = cached.as_ref() {
+            if *at == modified {
+                return Ok(key.clone());
+            }
+        }
+        let key = ApiKey::from_file(&self.path).map_err(|e| error(&e))?;
+        *cached = Some((modified, key.clone()));
+        Ok(key)
+    }
+}
+
+/// How a [`KeyPool`] picks among its available keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SelectionStrategy {
+    #[default]
+    RoundRobin,
+    LeastUsed,
+}
+
+#[derive(Debug)]
+struct PoolEntry {
+    key: ApiKey,
+    uses: u64,
+    quarantined: bool,
+    quarantined_until: Option<Instant>,
+}
+
+impl PoolEntry {
+    fn is_available(&self, now: Instant) -> bool {
+        !self.quarantined || self.quarantined_until.is_some_and(|until| now >= until)
+    }
+}
+
+#[derive(Debug)]
+struct PoolState {
+    entries: Vec<PoolEntry>,
+    next: usize,
+}
+
+/// A pool of keys rotated per request
+///
+/// Keys that fail with [`RequestErrors::Unauthorized`] or
+/// [`RequestErrors::InsufficientBalance`] are quarantined and skipped.
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::client::keys::{KeyPool, KeyProvider, SelectionStrategy};
+///
+/// let pool = KeyPool::new(vec!["key-a".to_string(), "key-b".to_string()])
+///     .with_strategy(SelectionStrategy::RoundRobin);
+/// assert_eq!(pool.key().unwrap().expose_secret(), "key-a");
+/// assert_eq!(pool.key().unwrap().expose_secret(), "key-b");
+/// ```
+#[derive(Debug)]
+pub struct KeyPool {
+    state: Mutex<PoolState>,
+    strategy: SelectionStrategy,
+    quarantine: Option<Duration>,
+}
+
+impl KeyPool {
+    pub fn new(keys: Vec<impl Into<ApiKey>>) -> Self {
+        let entries = keys
+            .into_iter()
+            .map(|key| PoolEntry {
+                key: key.into(),
+                uses: 0,
+                quarantined: false,
+                quarantined_until: None,
+            })
+            .collect();
+        KeyPool {
+            state: Mutex::new(PoolState { entries, next: 0 }),
+            strategy: SelectionStrategy::default(),
+            quarantine: None,
+        }
+    }
+
+    /// Sets how keys are picked
+    pub fn with_strategy(mut self, strategy: SelectionStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Releases quarantined keys after `duration` instead of keeping them out indefinitely
+    pub fn with_quarantine_duration(mut self, duration: Duration) -> Self {
+        self.quarantine = Some(duration);
+        self
+    }
+
+    /// Puts a quarantined key back into rotation
+    pub fn release(&self, key: &ApiKey) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(entry) = state.entries.iter_mut().find(|e| e.key == *key) {
+            entry.quarantined = false;
+        }
+    }
+
+    /// Returns the keys currently in quarantine
+    pub fn quarantined(&self) -> Vec<ApiKey> {
+        let now = Instant::now();
+        let state = self.state.lock().unwrap();
+        state
+            .entries
+            .iter()
+            .filter(|e| !e.is_available(now))
+            .map(|e| e.key.clone())
+            .collect()
+    }
+}
+
+impl KeyProvider for KeyPool {
+    fn key(&self) -> Result<ApiKey, RequestErrors> {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let len = state.entries.len();
+        let index = match self.strategy {
+            SelectionStrategy::RoundRobin => (0..len)
+                .map(|offset| (state.next + offset) % len)
+                .find(|&i| state.entries[i].is_available(now)),
+            SelectionStrategy::LeastUsed => (0..len)
+                .filter(|&i| state.entries[i].is_available(now))
+                .min_by_key(|&i| state.entries[i].uses),
+        };
+        let Some(index) = index else {
+            return Err(RequestErrors::NoApiKeyAvailable(
+                "all keys in the pool are quarantined".to_string(),
+            ));
+        };
+        state.next = (index + 1) % len;
+        let entry = &mut state.entries[index];
+        entry.uses += 1;
+        entry.quarantined = false;
+        Ok(entry.key.clone())
+    }
+
+    fn report_error(&self, key: &ApiKey, error: &RequestErrors) {
+        if !is_key_error(error) {
+            return;
+        }
+        let until = self.quarantine.map(|d| Instant::now() + d);
+        let mut state = self.state.lock().unwrap();
+        if let Some(entry) = state.entries.iter_mut().find(|e| e.key == *key) {
+            entry.quarantined = true;
+            entry.quarantined_until = until;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(keys: &[&str]) -> KeyPool {
+        KeyPool::new(keys.iter().map(|k| k.to_string()).collect())
+    }
+
+    fn key(provider: &impl KeyProvider) -> String {
+        provider.key().unwrap().expose_secret().to_string()
+    }
+
+    fn api_key(key: &str) -> ApiKey {
+        ApiKey::from(key.to_string())
+    }
+
+    #[test]
+    fn test_api_key() {
+        let key = ApiKey::new("sk-secret".to_string()).unwrap();
+        assert!(!format!("{:?}", key).contains("sk-secret"));
+        assert!(key.header_value().unwrap().is_sensitive());
+        assert!(matches!(
+            ApiKey::new("sk\nsecret".to_string()),
+            Err(ClientInitErrors::InvalidApiKey)
+        ));
+        assert!(matches!(
+            ApiKey::from("sk\r\n".to_string()).header_value(),
+            Err(ClientInitErrors::InvalidApiKey)
+        ));
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let pool = pool(&["a", "b", "c"]);
+        let keys: Vec<_> = (0..4).map(|_| key(&pool)).collect();
+        assert_eq!(keys, ["a", "b", "c", "a"]);
+    }
+
+    #[test]
+    fn test_least_used() {
+        let pool = pool(&["a", "b"]).with_strategy(SelectionStrategy::LeastUsed);
+        assert_eq!(key(&pool), "a");
+        assert_eq!(key(&pool), "b");
+        assert_eq!(key(&pool), "a");
+    }
+
+    #[test]
+    fn test_quarantine() {
+        let pool = pool(&["a", "b"]);
+        pool.report_error(&api_key("a"), &RequestErrors::Unauthorized(String::new()));
+        pool.report_error(
+            &api_key("b"),
+            &RequestErrors::RateLimitExceeded(String::new()),
+        );
+        assert_eq!(pool.quarantined(), [api_key("a")]);
+        assert_eq!(key(&pool), "b");
+        assert_eq!(key(&pool), "b");
+
+        pool.report_error(
+            &api_key("b"),
+            &RequestErrors::InsufficientBalance(String::new()),
+        );
+        assert!(matches!(
+            pool.key(),
+            Err(RequestErrors::NoApiKeyAvailable(_))
+        ));
+
+        pool.release(&api_key("a"));
+        assert_eq!(key(&pool), "a");
+    }
+
+    #[test]
+    fn test_quarantine_duration() {
+        let pool = pool(&["a"]).with_quarantine_duration(Duration::ZERO);
+        pool.report_error(&api_key("a"), &RequestErrors::Unauthorized(String::new()));
+        assert_eq!(key(&pool), "a");
+    }
+
+    #[test]
+    fn test_env_key() {
+        std::env::set_var("DEEP_SEEK_TEST_ENV_KEY", "from_env");
+        let provider = EnvKey::new("DEEP_SEEK_TEST_ENV_KEY".to_string());
+        assert_eq!(key(&provider), "from_env");
+        std::env::remove_var("DEEP_SEEK_TEST_ENV_KEY");
+        assert!(provider.key().is_err());
+    }
+
+    #[test]
+    fn test_file_key() {
+        let path = std::env::temp_dir().join(format!("deepseek_key_{}", std::process::id()));
+        std::fs::write(&path, "from_file\n").unwrap();
+        let provider = FileKey::new(&path);
+        assert_eq!(key(&provider), "from_file");
+        std::fs::write(&path, " first \nsecond\n").unwrap();
+        assert_eq!(ApiKey::from_file(&path).unwrap().expose_secret(), "first");
+        std::fs::remove_file(&path).unwrap();
+        assert!(provider.key().is_err());
+    }
+}
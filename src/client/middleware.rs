@@ -0,0 +1,274 @@
+//! Middleware run around every chat completion call, streaming or not
+//!
+//! Middleware see requests in the order they were added and results in reverse order,
+//! so the first middleware added is the outermost layer.
+//!
+//! Chat completions are the only endpoint this client calls. It has no FIM, model
+//! listing or balance endpoints, so middleware don't cover those.
+
+use std::sync::Arc;
+
+use http::HeaderMap;
+
+use super::chat_completions::{
+    request::RequestBody,
+    response::{ChatCompletionsChunk, ChatCompletionsResponse},
+};
+use crate::errors::request_errors::RequestErrors;
+
+/// Hooks around a chat completion call
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::{
+///     client::{
+///         chat_completions::{request::RequestBody, response::ChatCompletionsResponse},
+///         middleware::Middleware,
+///     },
+///     errors::request_errors::RequestErrors,
+///     DeepSeekClient,
+/// };
+/// use http::HeaderMap;
+///
+/// struct Audit;
+///
+/// impl Middleware for Audit {
+///     fn on_request(
+///         &self,
+///         request: &mut RequestBody,
+///         headers: &mut HeaderMap,
+///     ) -> Option<Result<ChatCompletionsResponse, RequestErrors>> {
+///         headers.insert("x-audit", "on".parse().unwrap());
+///         println!("sending {} messages", request.messages().len());
+///         None
+///     }
+/// }
+///
+/// let client = DeepSeekClient::new_with_api_key("key".to_string()).with_middleware(Audit);
+/// ```
+pub trait Middleware: Send + Sync {
+    /// Inspects or rewrites the outgoing request and adds headers to it
+    ///
+    /// Returning `Some` short-circuits the call: no request is sent, later middleware are
+    /// skipped, and the result is passed back through the middleware that already ran.
+    fn on_request(
+        &self,
+        _request: &mut RequestBody,
+        _headers: &mut HeaderMap,
+    ) -> Option<Result<ChatCompletionsResponse, RequestErrors>> {
+        None
+    }
+
+    /// Inspects or rewrites the result of a non-streaming call, or the error that
+    /// prevented a stream from starting
+    fn on_response(
+        &self,
+        _request: &RequestBody,
+        _result: &mut Result<ChatCompletionsResponse, RequestErrors>,
+    ) {
+    }
+
+    /// Inspects or rewrites each item of a stream
+    fn on_chunk(
+        &self,
+        _request: &RequestBody,
+        _chunk: &mut Result<ChatCompletionsChunk, RequestErrors>,
+    ) {
+    }
+}
+
+/// The middleware a call went through, used to unwind them in reverse order
+pub(crate) struct Pipeline {
+    middleware: Arc<[Arc<dyn Middleware>]>,
+    entered: usize,
+}
+
+impl Pipeline {
+    pub(crate) fn new(middleware: Arc<[Arc<dyn Middleware>]>) -> Self {
+        Pipeline {
+            middleware,
+            entered: 0,
+        }
+    }
+
+    pub(crate) fn request(
+        &mut self,
+        request: &mut RequestBody,
+        headers: &mut HeaderMap,
+    ) -> Option<Result<ChatCompletionsResponse, RequestErrors>> {
+        for middleware in self.middleware.iter() {
+            self.entered += 1;
+            if let Some(result) = middleware.on_request(request, headers) {
+                return Some(result);
+            }
+        }
+        None
+    }
+
+    fn entered(&self) -> impl Iterator<Item = &Arc<dyn Middleware>> {
+        self.middleware[..self.entered].iter().rev()
+    }
+
+    pub(crate) fn response(
+        &self,
+        request: &RequestBody,
+        result: &mut Result<ChatCompletionsResponse, RequestErrors>,
+    ) {
+        for middleware in self.entered() {
+            middleware.on_response(request, result);
+        }
+    }
+
+    pub(crate) fn chunk(
+        &self,
+        request: &RequestBody,
+        chunk: &mut Result<ChatCompletionsChunk, RequestErrors>,
+    ) {
+        for middleware in self.entered() {
+            middleware.on_chunk(request, chunk);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::{
+        client::chat_completions::{
+            request::{Message, Role},
+            response::{ChatCompletionsChoices, FinishReasons, Message as ResponseMessage, Usage},
+        },
+        DeepSeekClient,
+    };
+
+    fn response(content: &str) -> ChatCompletionsResponse {
+        ChatCompletionsResponse {
+            id: "cached".to_string(),
+            choices: vec![ChatCompletionsChoices {
+                finish_reason: FinishReasons::Stop,
+                index: 0,
+                message: ResponseMessage {
+                    content: Some(content.to_string()),
+                    reasoning_content: None,
+                    role: Role::Assistant,
+                    tool_calls: None,
+                },
+            }],
+            created: 0,
+            model: "deepseek-chat".to_string(),
+            object: "chat.completion".to_string(),
+            usage: Usage {
+                completion_tokens: 1,
+                prompt_tokens: 1,
+                prompt_cache_hit_tokens: 0,
+                prompt_cache_miss_tokens: 1,
+                total_tokens: 2,
+                completion_tokens_details: None,
+                prompt_tokens_details: None,
+            },
+            cache_hit: false,
+        }
+    }
+
+    struct Named {
+        name: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+        short_circuit: bool,
+    }
+
+    impl Middleware for Named {
+        fn on_request(
+            &self,
+            request: &mut RequestBody,
+            _headers: &mut HeaderMap,
+        ) -> Option<Result<ChatCompletionsResponse, RequestErrors>> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{} request", self.name));
+            let prompt = &request.messages()[0].content;
+            let prefixed = format!("{}:{}", self.name, prompt);
+            *request = request
+                .clone()
+                .with_messages(vec![Message::new_user_message(prefixed.clone())]);
+            self.short_circuit.then(|| Ok(response(&prefixed)))
+        }
+
+        fn on_response(
+            &self,
+            _request: &RequestBody,
+            result: &mut Result<ChatCompletionsResponse, RequestErrors>,
+        ) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{} response", self.name));
+            if let Ok(response) = result {
+                let content = response.choices[0].message.content.as_mut().unwrap();
+                content.push_str(&format!("+{}", self.name));
+            }
+        }
+
+        fn on_chunk(
+            &self,
+            _request: &RequestBody,
+            _chunk: &mut Result<ChatCompletionsChunk, RequestErrors>,
+        ) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{} chunk", self.name));
+        }
+    }
+
+    fn client(log: &Arc<Mutex<Vec<String>>>) -> DeepSeekClient {
+        let named = |name, short_circuit| Named {
+            name,
+            log: log.clone(),
+            short_circuit,
+        };
+        DeepSeekClient::new_with_api_key("key".to_string())
+            .with_middleware(named("a", false))
+            .with_middleware(named("b", true))
+            .with_middleware(named("c", false))
+    }
+
+    #[tokio::test]
+    async fn test_short_circuit_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let request = RequestBody::new_messages(vec![Message::new_user_message("hi".to_string())]);
+        let response = client(&log).chat_completions(request).await.unwrap();
+        assert_eq!(
+            response.choices[0].message.content.as_deref(),
+            Some("b:a:hi+b+a")
+        );
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["a request", "b request", "b response", "a response"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_short_circuit_stream() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let request = RequestBody::new_messages(vec![Message::new_user_message("hi".to_string())]);
+        let chunks: Vec<_> = client(&log)
+            .chat_completions_stream(request)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        let chunk = chunks[0].as_ref().unwrap();
+        assert_eq!(chunk.choices[0].delta.content.as_deref(), Some("b:a:hi"));
+        assert_eq!(chunk.choices[0].finish_reason, Some(FinishReasons::Stop));
+        assert!(chunk.usage.is_some());
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["a request", "b request", "b chunk", "a chunk"]
+        );
+    }
+}
@@ -1,2 +1,15 @@
+pub mod breaker;
+pub mod cache;
 pub mod chat_completions;
+#[allow(clippy::module_inception)]
 pub mod client;
+pub mod dedupe;
+pub mod hedge;
+pub mod keys;
+pub mod middleware;
+pub mod observer;
+pub mod provider;
+pub mod router;
+#[cfg(feature = "tower")]
+pub mod service;
+pub mod transport;
@@ -0,0 +1,264 @@
+//! Observation hooks for metrics exporters, plus `tracing` spans behind the `tracing` feature
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use super::chat_completions::{
+    request::{Model, RequestBody},
+    response::{ChatCompletionsChunk, Usage},
+};
+use crate::errors::request_errors::RequestErrors;
+
+/// Describes the request an event belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestInfo {
+    /// Path of the API endpoint, e.g. `chat/completions`
+    pub endpoint: &'static str,
+    pub model: Model,
+    pub stream: bool,
+    /// Starts at 1 and increases when the request is re-sent, e.g. after key failover
+    pub attempt: u32,
+    /// HTTP status of the current attempt, once the server has answered; lets
+    /// [`Observer::on_error`] tell a 429 from a 503
+    pub status: Option<u16>,
+}
+
+/// Describes a completed request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseInfo {
+    pub status: u16,
+    /// Time from the first attempt until the full response (or the end of the stream)
+    pub latency: Duration,
+    /// Time until the first chunk arrived; only set for streams
+    pub time_to_first_token: Option<Duration>,
+    /// Missing for streams that didn't request `include_usage`
+    pub usage: Option<Usage>,
+}
+
+/// Receives events for every request made by a [`DeepSeekClient`](crate::DeepSeekClient)
+///
+/// All methods default to doing nothing, so implementors only override what they export.
+pub trait Observer: Send + Sync {
+    /// Called before each attempt is sent
+    fn on_request(&self, _request: &RequestInfo) {}
+
+    /// Called once a request completed successfully
+    fn on_response(&self, _request: &RequestInfo, _response: &ResponseInfo) {}
+
+    /// Called when a request or stream fails
+    fn on_error(&self, _request: &RequestInfo, _error: &RequestErrors, _elapsed: Duration) {}
+
+    /// Called for every streamed chunk, with the time since the request started
+    fn on_chunk(&self, _request: &RequestInfo, _chunk: &ChatCompletionsChunk, _elapsed: Duration) {}
+}
+
+/// Tracks a single call and forwards its events to the observers and the tracing span
+pub(crate) struct Call {
+    info: RequestInfo,
+    observers: Arc<[Arc<dyn Observer>]>,
+    started: Instant,
+    first_chunk: Option<Duration>,
+    usage: Option<Usage>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl Call {
+    pub(crate) fn new(
+        observers: Arc<[Arc<dyn Observer>]>,
+        endpoint: &'static str,
+        request: &RequestBody,
+        stream: bool,
+    ) -> Self {
+        let info = RequestInfo {
+            endpoint,
+            model: request.model().clone(),
+            stream,
+            attempt: 0,
+            status: None,
+        };
+        Call {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "deepseek.request",
+                endpoint = info.endpoint,
+                model = %info.model,
+                stream = info.stream,
+                attempt = tracing::field::Empty,
+                status = tracing::field::Empty,
+                prompt_tokens = tracing::field::Empty,
+                completion_tokens = tracing::field::Empty,
+                cached_tokens = tracing::field::Empty,
+                time_to_first_token_ms = tracing::field::Empty,
+                latency_ms = tracing::field::Empty,
+                error = tracing::field::Empty,
+            ),
+            info,
+            observers,
+            started: Instant::now(),
+            first_chunk: None,
+            usage: None,
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    pub(crate) fn span(&self) -> &tracing::Span {
+        &self.span
+    }
+
+    pub(crate) fn attempt(&mut self) {
+        self.info.attempt += 1;
+        self.info.status = None;
+        #[cfg(feature = "tracing")]
+        self.span.record("attempt", self.info.attempt);
+        for observer in self.observers.iter() {
+            observer.on_request(&self.info);
+        }
+    }
+
+    pub(crate) fn status(&mut self, status: u16) {
+        self.info.status = Some(status);
+        #[cfg(feature = "tracing")]
+        self.span.record("status", status);
+    }
+
+    pub(crate) fn chunk(&mut self, chunk: &ChatCompletionsChunk) {
+        let elapsed = self.started.elapsed();
+        if self.first_chunk.is_none() {
+            self.first_chunk = Some(elapsed);
+            #[cfg(feature = "tracing")]
+            self.span
+                .record("time_to_first_token_ms", elapsed.as_millis() as u64);
+        }
+        if let Some(usage) = &chunk.usage {
+            self.usage = Some(usage.clone());
+        }
+        for observer in self.observers.iter() {
+            observer.on_chunk(&self.info, chunk, elapsed);
+        }
+    }
+
+    pub(crate) fn response(&mut self, usage: Option<&Usage>) {
+        if let Some(usage) = usage {
+            self.usage = Some(usage.clone());
+        }
+        let response = ResponseInfo {
+            status: self.info.status.unwrap_or_default(),
+            latency: self.started.elapsed(),
+            time_to_first_token: self.first_chunk,
+            usage: self.usage.clone(),
+        };
+        #[cfg(feature = "tracing")]
+        {
+            self.span
+                .record("latency_ms", response.latency.as_millis() as u64);
+            if let Some(usage) = &response.usage {
+                self.span.record("prompt_tokens", usage.prompt_tokens);
+                self.span
+                    .record("completion_tokens", usage.completion_tokens);
+                self.span
+                    .record("cached_tokens", usage.prompt_cache_hit_tokens);
+            }
+        }
+        for observer in self.observers.iter() {
+            observer.on_response(&self.info, &response);
+        }
+    }
+
+    pub(crate) fn error(&mut self, error: &RequestErrors) {
+        let elapsed = self.started.elapsed();
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("latency_ms", elapsed.as_millis() as u64);
+            self.span.record("error", tracing::field::display(error));
+            tracing::warn!(parent: &self.span, %error, "DeepSeek request failed");
+        }
+        for observer in self.observers.iter() {
+            observer.on_error(&self.info, error, elapsed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl Observer for Recorder {
+        fn on_request(&self, request: &RequestInfo) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("request {}", request.attempt));
+        }
+        fn on_response(&self, _request: &RequestInfo, response: &ResponseInfo) {
+            let tokens = response.usage.as_ref().map(|u| u.total_tokens);
+            self.0.lock().unwrap().push(format!(
+                "response {} {:?} {}",
+                response.status,
+                tokens,
+                response.time_to_first_token.is_some()
+            ));
+        }
+        fn on_error(&self, _request: &RequestInfo, error: &RequestErrors, _elapsed: Duration) {
+            self.0.lock().unwrap().push(format!("error {}", error));
+        }
+        fn on_chunk(&self, _request: &RequestInfo, chunk: &ChatCompletionsChunk, _: Duration) {
+            self.0.lock().unwrap().push(format!("chunk {}", chunk.id));
+        }
+    }
+
+    fn chunk(usage: Option<Usage>) -> ChatCompletionsChunk {
+        ChatCompletionsChunk {
+            id: "c".to_string(),
+            choices: vec![],
+            created: 0,
+            model: "deepseek-chat".to_string(),
+            object: "chat.completion.chunk".to_string(),
+            usage,
+        }
+    }
+
+    fn usage() -> Usage {
+        Usage {
+            completion_tokens: 1,
+            prompt_tokens: 2,
+            prompt_cache_hit_tokens: 0,
+            prompt_cache_miss_tokens: 2,
+            total_tokens: 3,
+            completion_tokens_details: None,
+            prompt_tokens_details: None,
+        }
+    }
+
+    #[test]
+    fn test_call_events() {
+        let recorder = Arc::new(Recorder::default());
+        let observers: Arc<[Arc<dyn Observer>]> = Arc::new([recorder.clone() as Arc<dyn Observer>]);
+        let mut call = Call::new(observers, "chat/completions", &RequestBody::default(), true);
+        call.attempt();
+        call.attempt();
+        call.status(200);
+        call.chunk(&chunk(None));
+        call.chunk(&chunk(Some(usage())));
+        call.response(None);
+        call.error(&RequestErrors::Forbidden);
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            [
+                "request 1",
+                "request 2",
+                "chunk c",
+                "chunk c",
+                "response 200 Some(3) true",
+                "error Forbidden",
+            ]
+        );
+    }
+}
@@ -0,0 +1,432 @@
+//! One interface for DeepSeek and other OpenAI-compatible servers
+//!
+//! [`ChatProvider`] is implemented by [`DeepSeekClient`] and [`OpenAiCompatibleClient`],
+//! so code written against [`RequestBody`] and [`ChatCompletionsResponse`] can target
+//! vLLM, Ollama, llama.cpp or OpenAI as well. A [`ProviderProfile`] describes how a
+//! server differs: where the endpoint lives, how the key is sent, which fields it
+//! rejects and what it calls each [`Model`].
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use futures::future::BoxFuture;
+use http::HeaderName;
+
+use super::{
+    breaker::CircuitBreaker,
+    chat_completions::stream::ChatCompletionsStream,
+    client::{DeepSeekClient, URL},
+    hedge::HedgePolicy,
+    keys::{KeyProvider, StaticKey},
+    middleware::Middleware,
+    observer::Observer,
+    transport::Transport,
+};
+use crate::{
+    errors::request_errors::RequestErrors,
+    types::{
+        request::{Model, RequestBody},
+        response::ChatCompletionsResponse,
+    },
+};
+
+/// A backend answering chat completion requests
+pub trait ChatProvider: Send + Sync {
+    /// Short name of the backend, e.g. for logs
+    fn name(&self) -> &str;
+
+    fn chat_completions(
+        &self,
+        request: RequestBody,
+    ) -> BoxFuture<'_, Result<ChatCompletionsResponse, RequestErrors>>;
+
+    fn chat_completions_stream(
+        &self,
+        request: RequestBody,
+    ) -> BoxFuture<'_, Result<ChatCompletionsStream, RequestErrors>>;
+}
+
+impl<T: ChatProvider + ?Sized> ChatProvider for Arc<T> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn chat_completions(
+        &self,
+        request: RequestBody,
+    ) -> BoxFuture<'_, Result<ChatCompletionsResponse, RequestErrors>> {
+        (**self).chat_completions(request)
+    }
+
+    fn chat_completions_stream(
+        &self,
+        request: RequestBody,
+    ) -> BoxFuture<'_, Result<ChatCompletionsStream, RequestErrors>> {
+        (**self).chat_completions_stream(request)
+    }
+}
+
+impl ChatProvider for DeepSeekClient {
+    fn name(&self) -> &str {
+        &self.profile.name
+    }
+
+    fn chat_completions(
+        &self,
+        request: RequestBody,
+    ) -> BoxFuture<'_, Result<ChatCompletionsResponse, RequestErrors>> {
+        Box::pin(DeepSeekClient::chat_completions(self, request))
+    }
+
+    fn chat_completions_stream(
+        &self,
+        request: RequestBody,
+    ) -> BoxFuture<'_, Result<ChatCompletionsStream, RequestErrors>> {
+        Box::pin(DeepSeekClient::chat_completions_stream(self, request))
+    }
+}
+
+/// How the API key is sent; no header is sent when the key is empty
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthScheme {
+    /// `Authorization: Bearer <key>`
+    Bearer,
+    /// The raw key in a custom header, e.g. `api-key`
+    Header(HeaderName),
+    /// Never send the key
+    None,
+}
+
+/// The quirks of an OpenAI-compatible server
+///
+/// Unsupported fields are removed from the request before it is sent. Names starting
+/// with `messages.` refer to fields of each message, e.g. `messages.prefix`.
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::{client::provider::ProviderProfile, request::Model};
+///
+/// let profile = ProviderProfile::ollama()
+///     .with_model_name(Model::DeepseekChat, "qwen2.5:7b")
+///     .with_model_name(Model::DeepSeekReasoner, "deepseek-r1:8b");
+/// assert_eq!(profile.name(), "ollama");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderProfile {
+    pub(crate) name: String,
+    pub(crate) url: String,
+    pub(crate) path: String,
+    pub(crate) auth: AuthScheme,
+    unsupported_fields: BTreeSet<String>,
+    model_names: BTreeMap<&'static str, String>,
+}
+
+/// Message fields only DeepSeek understands
+const DEEPSEEK_MESSAGE_FIELDS: [&str; 2] = ["messages.prefix", "messages.reasoning_content"];
+
+impl ProviderProfile {
+    /// A server at `url` serving `v1/chat/completions` with bearer authentication
+    pub fn new(name: impl Into<String>, url: impl Into<String>) -> Self {
+        ProviderProfile {
+            name: name.into(),
+            url: url.into(),
+            path: "v1/chat/completions".to_string(),
+            auth: AuthScheme::Bearer,
+            unsupported_fields: BTreeSet::new(),
+            model_names: BTreeMap::new(),
+        }
+    }
+
+    /// The DeepSeek API, which [`DeepSeekClient`] uses
+    pub fn deepseek() -> Self {
+        ProviderProfile::new("deepseek", URL).with_path("chat/completions")
+    }
+
+    /// The OpenAI API
+    pub fn openai() -> Self {
+        ProviderProfile::new("openai", "https://api.openai.com")
+            .without_fields(DEEPSEEK_MESSAGE_FIELDS)
+    }
+
+    /// A local vLLM server
+    pub fn vllm() -> Self {
+        ProviderProfile::new("vllm", "http://localhost:8000")
+            .without_fields(DEEPSEEK_MESSAGE_FIELDS)
+    }
+
+    /// A local Ollama server
+    pub fn ollama() -> Self {
+        ProviderProfile::new("ollama", "http://localhost:11434")
+            .without_fields(DEEPSEEK_MESSAGE_FIELDS)
+            .without_fields(["logprobs", "top_logprobs"])
+    }
+
+    /// A local llama.cpp server
+    pub fn llama_cpp() -> Self {
+        ProviderProfile::new("llama.cpp", "http://localhost:8080")
+            .without_fields(DEEPSEEK_MESSAGE_FIELDS)
+    }
+
+    /// Sets the endpoint path relative to the base URL
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    pub fn with_auth(mut self, auth: AuthScheme) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Removes `fields` from requests
+    pub fn without_fields<S: Into<String>>(mut self, fields: impl IntoIterator<Item = S>) -> Self {
+        self.unsupported_fields
+            .extend(fields.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sends `name` instead of the DeepSeek name of `model`
+    pub fn with_model_name(mut self, model: Model, name: impl Into<String>) -> Self {
+        self.model_names.insert(model.as_str(), name.into());
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the base URL used when none is given
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Serializes `request` for this server
+    pub(crate) fn encode(&self, request: &RequestBody) -> Result<Vec<u8>, RequestErrors> {
+        let encode_error = |e: serde_json::Error| RequestErrors::BuilderError(e.to_string());
+        if self.unsupported_fields.is_empty() && self.model_names.is_empty() {
+            return serde_json::to_vec(request).map_err(encode_error);
+        }
+        let mut value = serde_json::to_value(request).map_err(encode_error)?;
+        if let Some(object) = value.as_object_mut() {
+            if let Some(name) = self.model_names.get(request.model().as_str()) {
+                object.insert("model".to_string(), name.clone().into());
+            }
+            for field in &self.unsupported_fields {
+                match field.strip_prefix("messages.") {
+                    Some(field) => {
+                        let messages = object.get_mut("messages").and_then(|m| m.as_array_mut());
+                        for message in messages.into_iter().flatten() {
+                            if let Some(message) = message.as_object_mut() {
+                                message.remove(field);
+                            }
+                        }
+                    }
+                    None => {
+                        object.remove(field);
+                    }
+                }
+            }
+        }
+        serde_json::to_vec(&value).map_err(encode_error)
+    }
+}
+
+/// A client for any OpenAI-compatible server
+///
+/// It shares the machinery of [`DeepSeekClient`], including key providers, observers,
+/// middleware and transports, and adapts requests according to its profile.
+///
+/// # Example
+/// ```no_run
+/// use clia_deepseek_rs::{
+///     client::provider::{ChatProvider, OpenAiCompatibleClient, ProviderProfile},
+///     request::{Message, Model, RequestBody},
+///     DeepSeekClient,
+/// };
+///
+/// async fn ask(provider: &dyn ChatProvider, question: &str) -> String {
+///     let request = RequestBody::new_messages(vec![Message::new_user_message(question.to_string())]);
+///     let response = provider.chat_completions(request).await.unwrap();
+///     response.choices[0].message.content.clone().unwrap_or_default()
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let local = OpenAiCompatibleClient::new(
+///     ProviderProfile::ollama().with_model_name(Model::DeepseekChat, "qwen2.5:7b"),
+/// );
+/// let remote = DeepSeekClient::default().unwrap();
+/// println!("{}", ask(&local, "Hello").await);
+/// println!("{}", ask(&remote, "Hello").await);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct OpenAiCompatibleClient {
+    client: DeepSeekClient,
+}
+
+impl OpenAiCompatibleClient {
+    /// Connects to the profile's default URL without an API key
+    pub fn new(profile: ProviderProfile) -> Self {
+        Self::new_with_url(profile.url.clone(), profile)
+    }
+
+    pub fn new_with_url(url: String, profile: ProviderProfile) -> Self {
+        Self::from_client(
+            DeepSeekClient::new_with_url_and_key_provider(url, StaticKey::new(String::new())),
+            profile,
+        )
+    }
+
+    /// Applies `profile` to an already configured client
+    pub fn from_client(client: DeepSeekClient, profile: ProviderProfile) -> Self {
+        OpenAiCompatibleClient {
+            client: client.with_profile(profile),
+        }
+    }
+
+    pub fn with_api_key(self, api_key: String) -> Self {
+        self.map(|client| client.with_api_key(api_key))
+    }
+
+    pub fn with_key_provider(self, provider: impl KeyProvider + 'static) -> Self {
+        self.map(|client| client.with_key_provider(provider))
+    }
+
+    pub fn with_transport(self, transport: impl Transport + 'static) -> Self {
+        self.map(|client| client.with_transport(transport))
+    }
+
+    pub fn with_observer(self, observer: impl Observer + 'static) -> Self {
+        self.map(|client| client.with_observer(observer))
+    }
+
+    pub fn with_middleware(self, middleware: impl Middleware + 'static) -> Self {
+        self.map(|client| client.with_middleware(middleware))
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.map(|client| client.with_timeout(timeout))
+    }
+
+    pub fn with_stream_idle_timeout(self, timeout: Duration) -> Self {
+        self.map(|client| client.with_stream_idle_timeout(timeout))
+    }
+
+    pub fn with_circuit_breaker(self, breaker: CircuitBreaker) -> Self {
+        self.map(|client| client.with_circuit_breaker(breaker))
+    }
+
+    pub fn with_hedging(self, policy: HedgePolicy) -> Self {
+        self.map(|client| client.with_hedging(policy))
+    }
+
+    pub fn with_deduplication(self) -> Self {
+        self.map(|client| client.with_deduplication())
+    }
+
+    /// Returns the underlying client
+    pub fn client(&self) -> &DeepSeekClient {
+        &self.client
+    }
+
+    fn map(self, f: impl FnOnce(DeepSeekClient) -> DeepSeekClient) -> Self {
+        OpenAiCompatibleClient {
+            client: f(self.client),
+        }
+    }
+
+    pub async fn chat_completions(
+        &self,
+        request: RequestBody,
+    ) -> Result<ChatCompletionsResponse, RequestErrors> {
+        self.client.chat_completions(request).await
+    }
+
+    pub async fn chat_completions_stream(
+        &self,
+        request: RequestBody,
+    ) -> Result<ChatCompletionsStream, RequestErrors> {
+        self.client.chat_completions_stream(request).await
+    }
+}
+
+impl ChatProvider for OpenAiCompatibleClient {
+    fn name(&self) -> &str {
+        ChatProvider::name(&self.client)
+    }
+
+    fn chat_completions(
+        &self,
+        request: RequestBody,
+    ) -> BoxFuture<'_, Result<ChatCompletionsResponse, RequestErrors>> {
+        ChatProvider::chat_completions(&self.client, request)
+    }
+
+    fn chat_completions_stream(
+        &self,
+        request: RequestBody,
+    ) -> BoxFuture<'_, Result<ChatCompletionsStream, RequestErrors>> {
+        ChatProvider::chat_completions_stream(&self.client, request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{header::AUTHORIZATION, StatusCode};
+
+    use super::*;
+    use crate::{client::transport::MemoryTransport, types::request::Message};
+
+    const OLLAMA_RESPONSE: &str = r#"{"id":"chatcmpl-1","object":"chat.completion","created":1,"model":"qwen2.5:7b","choices":[{"index":0,"message":{"role":"assistant","content":"Hi"},"finish_reason":"stop"}],"usage":{"prompt_tokens":5,"completion_tokens":1,"total_tokens":6}}"#;
+
+    fn request() -> RequestBody {
+        RequestBody::new_messages(vec![
+            Message::new_user_message("Hello".to_string()),
+            Message::new_assistant_prefix_message("Hi".to_string()),
+        ])
+        .with_logprobs(true)
+    }
+
+    #[test]
+    fn test_encode() {
+        let profile = ProviderProfile::ollama().with_model_name(Model::DeepseekChat, "qwen2.5:7b");
+        let body: serde_json::Value =
+            serde_json::from_slice(&profile.encode(&request()).unwrap()).unwrap();
+        assert_eq!(body["model"], "qwen2.5:7b");
+        assert!(body.get("logprobs").is_none());
+        assert!(body["messages"][1].get("prefix").is_none());
+
+        let body: serde_json::Value =
+            serde_json::from_slice(&ProviderProfile::deepseek().encode(&request()).unwrap())
+                .unwrap();
+        assert_eq!(body["model"], "deepseek-chat");
+        assert_eq!(body["messages"][1]["prefix"], true);
+    }
+
+    #[tokio::test]
+    async fn test_openai_compatible_client() {
+        let transport = Arc::new(MemoryTransport::new());
+        transport.push_response(StatusCode::OK, OLLAMA_RESPONSE);
+        transport.push_response(StatusCode::OK, OLLAMA_RESPONSE);
+        let profile = ProviderProfile::ollama()
+            .with_auth(AuthScheme::Header(HeaderName::from_static("api-key")));
+        let client = OpenAiCompatibleClient::new(profile).with_transport(transport.clone());
+        let provider: &dyn ChatProvider = &client;
+        assert_eq!(provider.name(), "ollama");
+
+        let response = provider.chat_completions(request()).await.unwrap();
+        assert_eq!(response.usage.prompt_cache_hit_tokens, 0);
+        let sent = &transport.requests()[0];
+        assert_eq!(sent.url, "http://localhost:11434/v1/chat/completions");
+        assert!(sent.headers.get(AUTHORIZATION).is_none());
+        assert!(sent.headers.get("api-key").is_none());
+
+        let client = client.with_api_key("secret".to_string());
+        client.chat_completions(request()).await.unwrap();
+        assert_eq!(transport.requests()[1].headers["api-key"], "secret");
+    }
+}
@@ -0,0 +1,472 @@
+//! Spreading requests over several backends with failover
+//!
+//! A [`Router`] holds a list of [`Endpoint`]s, each wrapping a [`ChatProvider`] such as
+//! a [`DeepSeekClient`](crate::DeepSeekClient) with its own base URL, key or model.
+//! The [`RoutingStrategy`] picks which endpoint is tried first; when it fails, the
+//! remaining endpoints are tried in the order they were added. Each endpoint has a
+//! [`CircuitBreaker`] so a failing backend is skipped until its cooldown ends.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::future::BoxFuture;
+
+use super::{
+    breaker::{is_backend_failure, BreakerState, CircuitBreaker},
+    chat_completions::stream::ChatCompletionsStream,
+    provider::ChatProvider,
+};
+use crate::{
+    errors::request_errors::RequestErrors,
+    types::{
+        request::{Model, RequestBody},
+        response::{ChatCompletionsResponse, FinishReasons},
+    },
+};
+
+/// How a [`Router`] picks the first endpoint to try
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoutingStrategy {
+    /// Always the first healthy endpoint
+    #[default]
+    Priority,
+    /// Endpoints in proportion to their weight
+    WeightedRoundRobin,
+    /// The endpoint with the lowest recent latency of successful requests; endpoints
+    /// without measurements are tried first, unless they have only failed so far
+    LatencyAware,
+}
+
+#[derive(Debug, Default)]
+struct EndpointStats {
+    requests: u64,
+    failures: u64,
+    latency: Option<Duration>,
+}
+
+/// A backend of a [`Router`]
+pub struct Endpoint {
+    provider: Arc<dyn ChatProvider>,
+    weight: u32,
+    model: Option<Model>,
+    breaker: CircuitBreaker,
+    stats: Mutex<EndpointStats>,
+}
+
+impl Endpoint {
+    pub fn new(provider: impl ChatProvider + 'static) -> Self {
+        Endpoint {
+            provider: Arc::new(provider),
+            weight: 1,
+            model: None,
+            breaker: CircuitBreaker::default(),
+            stats: Mutex::new(EndpointStats::default()),
+        }
+    }
+
+    /// Sets the share of traffic under [`RoutingStrategy::WeightedRoundRobin`]; an
+    /// endpoint with weight 0 only serves as a fallback
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Sends requests to this endpoint with `model` instead of the requested one
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    pub fn with_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    fn record_success(&self, latency: Duration) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.requests += 1;
+        self.breaker.record_success();
+        // Exponentially weighted, so the estimate follows recent conditions
+        stats.latency = Some(match stats.latency {
+            Some(average) => average.mul_f64(0.7) + latency.mul_f64(0.3),
+            None => latency,
+        });
+    }
+
+    /// Counts a failed request; only backend failures trip the breaker
+    fn record_failure(&self, backend: bool) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.requests += 1;
+        stats.failures += 1;
+        if backend {
+            self.breaker.record_failure();
+        }
+    }
+}
+
+/// Health of one endpoint, as reported by [`Router::health`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointHealth {
+    pub name: String,
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    /// Recent average latency of successful requests
+    pub latency: Option<Duration>,
+    pub requests: u64,
+    pub failures: u64,
+}
+
+/// Routes requests over several endpoints with failover
+///
+/// Any error except [`RequestErrors::BadRequest`] moves on to the next endpoint, as
+/// does a response cut short with [`FinishReasons::InsufficientSystemResource`]. If
+/// every endpoint fails, the last error is returned, or the last degraded response if
+/// there was one. Connection errors, timeouts, 5xx statuses and degraded responses
+/// count against an endpoint's circuit breaker.
+///
+/// # Example
+/// ```no_run
+/// use std::time::Duration;
+/// use clia_deepseek_rs::{
+///     client::{
+///         breaker::CircuitBreaker,
+///         router::{Endpoint, Router, RoutingStrategy},
+///     },
+///     request::{Message, RequestBody},
+///     DeepSeekClient,
+/// };
+/// # #[tokio::main]
+/// # async fn main() {
+/// let router = Router::new(RoutingStrategy::Priority)
+///     .with_endpoint(Endpoint::new(DeepSeekClient::default().unwrap()))
+///     .with_endpoint(
+///         Endpoint::new(DeepSeekClient::new_with_url_and_api_key(
+///             "https://gateway.example.com".to_string(),
+///             "key".to_string(),
+///         ))
+///         .with_breaker(CircuitBreaker::new(3, Duration::from_secs(60))),
+///     );
+/// let request = RequestBody::new_messages(vec![Message::new_user_message("Hello".to_string())]);
+/// let response = router.chat_completions(request).await;
+/// for endpoint in router.health() {
+///     println!("{}: {:?}", endpoint.name, endpoint.state);
+/// }
+/// # }
+/// ```
+pub struct Router {
+    endpoints: Vec<Endpoint>,
+    strategy: RoutingStrategy,
+    /// Current weights of the smooth weighted round-robin
+    current_weights: Mutex<Vec<i64>>,
+}
+
+impl Router {
+    pub fn new(strategy: RoutingStrategy) -> Self {
+        Router {
+            endpoints: Vec::new(),
+            strategy,
+            current_weights: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn with_endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.endpoints.push(endpoint);
+        self.current_weights.get_mut().unwrap().push(0);
+        self
+    }
+
+    /// Returns the health of every endpoint, in the order they were added
+    pub fn health(&self) -> Vec<EndpointHealth> {
+        self.endpoints
+            .iter()
+            .map(|endpoint| {
+                let stats = endpoint.stats.lock().unwrap();
+                EndpointHealth {
+                    name: endpoint.provider.name().to_string(),
+                    state: endpoint.breaker.state(),
+                    consecutive_failures: endpoint.breaker.consecutive_failures(),
+                    latency: stats.latency,
+                    requests: stats.requests,
+                    failures: stats.failures,
+                }
+            })
+            .collect()
+    }
+
+    pub async fn chat_completions(
+        &self,
+        request: RequestBody,
+    ) -> Result<ChatCompletionsResponse, RequestErrors> {
+        self.route(
+            request,
+            |provider, request| provider.chat_completions(request),
+            |response: &ChatCompletionsResponse| {
+                response
+                    .choices
+                    .iter()
+                    .any(|choice| choice.finish_reason == FinishReasons::InsufficientSystemResource)
+            },
+        )
+        .await
+    }
+
+    /// Opens a stream on the first endpoint that accepts the request; once chunks
+    /// flow, errors are not retried elsewhere
+    pub async fn chat_completions_stream(
+        &self,
+        request: RequestBody,
+    ) -> Result<ChatCompletionsStream, RequestErrors> {
+        self.route(
+            request,
+            |provider, request| provider.chat_completions_stream(request),
+            |_| false,
+        )
+        .await
+    }
+
+    async fn route<'a, T>(
+        &'a self,
+        request: RequestBody,
+        send: impl Fn(&'a dyn ChatProvider, RequestBody) -> BoxFuture<'a, Result<T, RequestErrors>>,
+        degraded: impl Fn(&T) -> bool,
+    ) -> Result<T, RequestErrors> {
+        let mut last_error = None;
+        let mut fallback = None;
+        for index in self.order() {
+            let endpoint = &self.endpoints[index];
+            if !endpoint.breaker.allow() {
+                last_error.get_or_insert_with(|| {
+                    RequestErrors::CircuitOpen(endpoint.provider.name().to_string())
+                });
+                continue;
+            }
+            let request = match &endpoint.model {
+                Some(model) => request.clone().with_model(model.clone()),
+                None => request.clone(),
+            };
+            let started = Instant::now();
+            let result = send(endpoint.provider.as_ref(), request).await;
+            match &result {
+                Ok(value) if degraded(value) => endpoint.record_failure(true),
+                Ok(_) => endpoint.record_success(started.elapsed()),
+                Err(e) => endpoint.record_failure(is_backend_failure(e)),
+            }
+            match result {
+                Ok(value) if degraded(&value) => fallback = Some(value),
+                Ok(value) => return Ok(value),
+                Err(e @ RequestErrors::BadRequest(_)) => return Err(e),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        match (fallback, last_error) {
+            (Some(value), _) => Ok(value),
+            (None, Some(e)) => Err(e),
+            (None, None) => Err(RequestErrors::BuilderError(
+                "router has no endpoints".to_string(),
+            )),
+        }
+    }
+
+    /// Returns endpoint indices in the order they should be tried
+    fn order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.endpoints.len()).collect();
+        match self.strategy {
+            RoutingStrategy::Priority => {}
+            RoutingStrategy::WeightedRoundRobin => {
+                if let Some(first) = self.next_weighted() {
+                    order.retain(|&i| i != first);
+                    order.insert(0, first);
+                }
+            }
+            RoutingStrategy::LatencyAware => {
+                let latencies: Vec<Duration> = self
+                    .endpoints
+                    .iter()
+                    .map(|e| {
+                        let stats = e.stats.lock().unwrap();
+                        match stats.latency {
+                            Some(latency) => latency,
+                            None if stats.failures > 0 => Duration::MAX,
+                            None => Duration::ZERO,
+                        }
+                    })
+                    .collect();
+                order.sort_by_key(|&i| latencies[i]);
+            }
+        }
+        order
+    }
+
+    /// Picks an endpoint with nginx's smooth weighted round-robin, which spreads
+    /// heavier endpoints out instead of sending them bursts
+    fn next_weighted(&self) -> Option<usize> {
+        let mut current = self.current_weights.lock().unwrap();
+        let total: i64 = self.endpoints.iter().map(|e| i64::from(e.weight)).sum();
+        if total == 0 {
+            return None;
+        }
+        for (weight, endpoint) in current.iter_mut().zip(&self.endpoints) {
+            *weight += i64::from(endpoint.weight);
+        }
+        let (best, _) = current
+            .iter()
+            .enumerate()
+            .max_by_key(|&(i, weight)| (*weight, std::cmp::Reverse(i)))?;
+        current[best] -= total;
+        Some(best)
+    }
+}
+
+impl ChatProvider for Router {
+    fn name(&self) -> &str {
+        "router"
+    }
+
+    fn chat_completions(
+        &self,
+        request: RequestBody,
+    ) -> BoxFuture<'_, Result<ChatCompletionsResponse, RequestErrors>> {
+        Box::pin(Router::chat_completions(self, request))
+    }
+
+    fn chat_completions_stream(
+        &self,
+        request: RequestBody,
+    ) -> BoxFuture<'_, Result<ChatCompletionsStream, RequestErrors>> {
+        Box::pin(Router::chat_completions_stream(self, request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::testing::{response, FakeProvider};
+
+    /// Answers with queued outcomes, or its name once the queue is empty
+    fn fake(name: &'static str) -> FakeProvider {
+        FakeProvider::new(move |_| Ok(response(name))).with_name(name)
+    }
+
+    fn unavailable() -> RequestErrors {
+        RequestErrors::StatusError(StatusCode::SERVICE_UNAVAILABLE, String::new())
+    }
+
+    async fn answer(router: &Router) -> String {
+        let response = router
+            .chat_completions(RequestBody::default())
+            .await
+            .unwrap();
+        response.choices[0].message.content.clone().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_priority_failover_and_breaker() {
+        let router = Router::new(RoutingStrategy::Priority)
+            .with_endpoint(
+                Endpoint::new(
+                    fake("primary")
+                        .then(Err(unavailable()))
+                        .then(Err(unavailable())),
+                )
+                .with_breaker(CircuitBreaker::new(2, Duration::from_secs(60))),
+            )
+            .with_endpoint(Endpoint::new(fake("secondary")));
+        assert_eq!(answer(&router).await, "secondary");
+        assert_eq!(answer(&router).await, "secondary");
+        // The primary's breaker is open now, so it isn't tried even though it recovered
+        assert_eq!(answer(&router).await, "secondary");
+
+        let health = router.health();
+        assert_eq!(health[0].state, BreakerState::Open);
+        assert_eq!((health[0].requests, health[0].failures), (2, 2));
+        assert_eq!(health[1].requests, 3);
+        assert!(health[1].latency.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_bad_request_and_degraded_responses() {
+        let mut degraded = response("partial");
+        degraded.choices[0].finish_reason = FinishReasons::InsufficientSystemResource;
+        let router = Router::new(RoutingStrategy::Priority)
+            .with_endpoint(Endpoint::new(
+                fake("a")
+                    .then(Err(RequestErrors::BadRequest(String::new())))
+                    .then(Ok(degraded)),
+            ))
+            .with_endpoint(Endpoint::new(fake("b").then(Err(unavailable()))));
+        assert!(matches!(
+            router.chat_completions(RequestBody::default()).await,
+            Err(RequestErrors::BadRequest(_))
+        ));
+        // Both fail; the degraded response beats the error
+        assert_eq!(answer(&router).await, "partial");
+    }
+
+    #[tokio::test]
+    async fn test_weighted_round_robin() {
+        let router = Router::new(RoutingStrategy::WeightedRoundRobin)
+            .with_endpoint(Endpoint::new(fake("a")).with_weight(3))
+            .with_endpoint(Endpoint::new(fake("b")).with_weight(1))
+            .with_endpoint(Endpoint::new(fake("c")).with_weight(0));
+        let mut answers = Vec::new();
+        for _ in 0..8 {
+            answers.push(answer(&router).await);
+        }
+        assert_eq!(answers, ["a", "a", "b", "a", "a", "a", "b", "a"]);
+    }
+
+    #[tokio::test]
+    async fn test_latency_aware() {
+        let slow = fake("slow").with_delay(Duration::from_millis(30));
+        let slow_calls = slow.requests();
+        let router = Router::new(RoutingStrategy::LatencyAware)
+            .with_endpoint(Endpoint::new(slow))
+            .with_endpoint(Endpoint::new(fake("fast")));
+        // Both are measured once, then the faster one wins
+        assert_eq!(answer(&router).await, "slow");
+        assert_eq!(answer(&router).await, "fast");
+        assert_eq!(answer(&router).await, "fast");
+        assert_eq!(slow_calls.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_latency_aware_ignores_fast_errors() {
+        let limited = fake("limited")
+            .then(Err(RequestErrors::RateLimitExceeded(String::new())))
+            .then(Err(RequestErrors::RateLimitExceeded(String::new())));
+        let limited_calls = limited.requests();
+        let slow = fake("slow").with_delay(Duration::from_millis(20));
+        let router = Router::new(RoutingStrategy::LatencyAware)
+            .with_endpoint(Endpoint::new(limited))
+            .with_endpoint(Endpoint::new(slow));
+        assert_eq!(answer(&router).await, "slow");
+        assert_eq!(answer(&router).await, "slow");
+        assert_eq!(limited_calls.lock().unwrap().len(), 1);
+
+        let health = router.health();
+        assert_eq!((health[0].requests, health[0].failures), (1, 1));
+        assert_eq!(health[0].latency, None);
+        // Rate limits don't say the backend is down
+        assert_eq!(health[0].consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn test_all_open() {
+        let router = Router::new(RoutingStrategy::Priority).with_endpoint(
+            Endpoint::new(fake("only").then(Err(unavailable())))
+                .with_breaker(CircuitBreaker::new(1, Duration::from_secs(60))),
+        );
+        assert!(router
+            .chat_completions(RequestBody::default())
+            .await
+            .is_err());
+        assert!(matches!(
+            router.chat_completions(RequestBody::default()).await,
+            Err(RequestErrors::CircuitOpen(name)) if name == "only"
+        ));
+    }
+}
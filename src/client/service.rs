@@ -0,0 +1,77 @@
+//! `tower::Service` for [`DeepSeekClient`]
+//!
+//! The client is a `Service<RequestBody>` answering with the full
+//! [`ChatCompletionsResponse`], so it composes with tower layers such as
+//! `tower::limit`, `tower::retry` and `tower::buffer`.
+//!
+//! # Example
+//! ```no_run
+//! use clia_deepseek_rs::{request::{Message, RequestBody}, DeepSeekClient};
+//! use tower::{Service, ServiceBuilder, ServiceExt};
+//! # #[tokio::main]
+//! # async fn main() {
+//! let mut service = ServiceBuilder::new()
+//!     .concurrency_limit(4)
+//!     .service(DeepSeekClient::default().unwrap());
+//! let request = RequestBody::new_messages(vec![Message::new_user_message("Hello".to_string())]);
+//! let response = service.ready().await.unwrap().call(request).await.unwrap();
+//! # }
+//! ```
+
+use std::task::{Context, Poll};
+
+use futures::future::BoxFuture;
+use tower::Service;
+
+use super::client::DeepSeekClient;
+use crate::{
+    errors::request_errors::RequestErrors,
+    types::{request::RequestBody, response::ChatCompletionsResponse},
+};
+
+impl Service<RequestBody> for DeepSeekClient {
+    type Response = ChatCompletionsResponse;
+    type Error = RequestErrors;
+    type Future = BoxFuture<'static, Result<ChatCompletionsResponse, RequestErrors>>;
+
+    /// Always ready; use `tower::limit` to apply backpressure
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), RequestErrors>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestBody) -> Self::Future {
+        let client = self.clone();
+        Box::pin(async move { client.chat_completions(request).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use http::StatusCode;
+    use tower::{ServiceBuilder, ServiceExt};
+
+    use super::*;
+    use crate::{
+        client::transport::MemoryTransport, testing::response_json, types::request::Message,
+    };
+
+    #[tokio::test]
+    async fn test_service_with_layers() {
+        let transport = Arc::new(MemoryTransport::new());
+        transport.push_json(&response_json("Hi"));
+        transport.push_response(StatusCode::TOO_MANY_REQUESTS, "slow down");
+        let client = DeepSeekClient::new_with_api_key("key".to_string()).with_transport(transport);
+        let service = ServiceBuilder::new().concurrency_limit(1).service(client);
+        let request =
+            RequestBody::new_messages(vec![Message::new_user_message("Hello".to_string())]);
+
+        let response = service.clone().oneshot(request.clone()).await.unwrap();
+        assert_eq!(response.choices[0].message.content.as_deref(), Some("Hi"));
+        assert!(matches!(
+            service.oneshot(request).await,
+            Err(RequestErrors::RateLimitExceeded(_))
+        ));
+    }
+}
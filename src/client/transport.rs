@@ -0,0 +1,218 @@
+//! The HTTP layer underneath [`DeepSeekClient`](crate::DeepSeekClient)
+//!
+//! The client builds an [`HttpRequest`] and hands it to a [`Transport`], which returns
+//! the status, headers and a body stream. [`ReqwestTransport`] is used by default;
+//! other HTTP stacks, such as hyper, a custom TLS setup or a browser `fetch` wrapper,
+//! only need to implement [`Transport`]. [`MemoryTransport`] serves canned responses
+//! for tests.
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::{future::BoxFuture, Stream, StreamExt};
+pub use http::{HeaderMap, Method, StatusCode};
+
+use crate::errors::request_errors::RequestErrors;
+
+/// A response body, delivered in chunks as they arrive
+pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Vec<u8>, RequestErrors>> + Send>>;
+
+/// A request for the transport to send
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    /// Limit for the whole exchange, including reading the body
+    pub timeout: Option<Duration>,
+}
+
+/// A response whose body hasn't necessarily been read yet
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: ByteStream,
+}
+
+impl HttpResponse {
+    /// Builds a response with a body that is already complete
+    pub fn new(status: StatusCode, body: impl Into<Vec<u8>>) -> Self {
+        let body = body.into();
+        HttpResponse {
+            status,
+            headers: HeaderMap::new(),
+            body: Box::pin(futures::stream::once(async move { Ok(body) })),
+        }
+    }
+
+    /// Reads the whole body
+    pub async fn bytes(mut self) -> Result<Vec<u8>, RequestErrors> {
+        let mut bytes = Vec::new();
+        while let Some(chunk) = self.body.next().await {
+            bytes.extend_from_slice(&chunk?);
+        }
+        Ok(bytes)
+    }
+
+    /// Reads the whole body as text, replacing invalid UTF-8
+    pub async fn text(self) -> Result<String, RequestErrors> {
+        Ok(String::from_utf8_lossy(&self.bytes().await?).into_owned())
+    }
+}
+
+impl fmt::Debug for HttpResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HttpResponse")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Sends HTTP requests for the client
+///
+/// Failures to reach the server are reported as errors; any response, whatever its
+/// status, is returned as `Ok` and mapped to an error by the client.
+pub trait Transport: Send + Sync {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, RequestErrors>>;
+}
+
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, RequestErrors>> {
+        (**self).send(request)
+    }
+}
+
+/// The default transport, backed by a `reqwest::Client`
+#[cfg(feature = "reqwest")]
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestTransport {
+    pub fn new() -> Self {
+        ReqwestTransport::default()
+    }
+}
+
+/// Uses a preconfigured client, e.g. one with client certificates or a proxy
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Client> for ReqwestTransport {
+    fn from(client: reqwest::Client) -> Self {
+        ReqwestTransport { client }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Transport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, RequestErrors>> {
+        Box::pin(async move {
+            let mut builder = self
+                .client
+                .request(request.method, request.url)
+                .headers(request.headers)
+                .body(request.body);
+            if let Some(timeout) = request.timeout {
+                builder = builder.timeout(timeout);
+            }
+            let res = builder.send().await.map_err(RequestErrors::from)?;
+            Ok(HttpResponse {
+                status: res.status(),
+                headers: res.headers().clone(),
+                body: Box::pin(
+                    res.bytes_stream()
+                        .map(|chunk| chunk.map(|b| b.to_vec()).map_err(RequestErrors::from)),
+                ),
+            })
+        })
+    }
+}
+
+/// Stands in when the crate is built without a transport
+#[cfg(not(feature = "reqwest"))]
+#[derive(Debug)]
+struct MissingTransport;
+
+#[cfg(not(feature = "reqwest"))]
+impl Transport for MissingTransport {
+    fn send(&self, _request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, RequestErrors>> {
+        Box::pin(async {
+            Err(RequestErrors::BuilderError(
+                "no HTTP transport; enable the `reqwest` feature or call `with_transport`"
+                    .to_string(),
+            ))
+        })
+    }
+}
+
+pub(crate) fn default_transport() -> Arc<dyn Transport> {
+    #[cfg(feature = "reqwest")]
+    return Arc::new(ReqwestTransport::new());
+    #[cfg(not(feature = "reqwest"))]
+    return Arc::new(MissingTransport);
+}
+
+/// Answers requests with queued responses and records what was sent
+///
+/// Once the queue is empty, requests fail with [`RequestErrors::ConnectionError`].
+///
+/// # Example
+/// ```
+/// use std::sync::Arc;
+/// use clia_deepseek_rs::{client::transport::{MemoryTransport, StatusCode}, DeepSeekClient};
+///
+/// let transport = Arc::new(MemoryTransport::new());
+/// transport.push_response(StatusCode::UNAUTHORIZED, "invalid key");
+/// let client = DeepSeekClient::new_with_api_key("key".to_string())
+///     .with_transport(transport.clone());
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryTransport {
+    responses: Mutex<VecDeque<(StatusCode, Vec<u8>)>>,
+    requests: Mutex<Vec<HttpRequest>>,
+}
+
+impl MemoryTransport {
+    pub fn new() -> Self {
+        MemoryTransport::default()
+    }
+
+    /// Queues a response
+    pub fn push_response(&self, status: StatusCode, body: impl Into<Vec<u8>>) {
+        self.responses
+            .lock()
+            .unwrap()
+            .push_back((status, body.into()));
+    }
+
+    /// Queues a `200 OK` response with `body` serialized as JSON
+    pub fn push_json(&self, body: &impl serde::Serialize) {
+        let body = serde_json::to_vec(body).unwrap_or_default();
+        self.push_response(StatusCode::OK, body);
+    }
+
+    /// Returns the requests sent so far
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, RequestErrors>> {
+        self.requests.lock().unwrap().push(request);
+        let response = self.responses.lock().unwrap().pop_front();
+        Box::pin(async move {
+            let (status, body) = response
+                .ok_or_else(|| RequestErrors::ConnectionError("no response queued".to_string()))?;
+            Ok(HttpResponse::new(status, body))
+        })
+    }
+}
@@ -0,0 +1,187 @@
+//! Markdown transcripts and OpenAI chat fine-tuning JSONL
+
+use serde::{Deserialize, Serialize};
+
+use super::{Conversation, Turn};
+use crate::{
+    errors::conversation_errors::ConversationErrors,
+    types::{
+        request::{Message, RequestBody, Role},
+        response::ToolsCall,
+    },
+};
+
+fn role_title(role: &Role) -> &'static str {
+    match role {
+        Role::System => "System",
+        Role::User => "User",
+        Role::Assistant => "Assistant",
+        Role::Tool => "Tool",
+    }
+}
+
+impl Conversation {
+    /// Renders a human-readable Markdown transcript
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# Conversation\n\n**Model:** {}\n", self.settings.model());
+        for turn in &self.turns {
+            out.push_str(&format!("\n## {}", role_title(&turn.message.role)));
+            if let Some(name) = &turn.message.name {
+                out.push_str(&format!(" ({})", name));
+            }
+            if let Some(id) = &turn.message.tool_call_id {
+                out.push_str(&format!(" ({})", id));
+            }
+            out.push_str("\n\n");
+            if let Some(reasoning) = &turn.reasoning_content {
+                out.push_str(&format!(
+                    "<details>\n<summary>Reasoning</summary>\n\n{}\n\n</details>\n\n",
+                    reasoning.trim()
+                ));
+            }
+            out.push_str(turn.message.content.trim());
+            out.push('\n');
+            for call in turn.message.tool_calls.iter().flatten() {
+                out.push_str(&format!(
+                    "\n`{}({})` ({})\n",
+                    call.function_call.name, call.function_call.arguments, call.id
+                ));
+            }
+            if let Some(usage) = &turn.usage {
+                out.push_str(&format!(
+                    "\n*Tokens: {} prompt, {} completion, {} total*\n",
+                    usage.prompt_tokens, usage.completion_tokens, usage.total_tokens
+                ));
+            }
+        }
+        out
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct FineTuningRecord {
+    messages: Vec<FineTuningMessage>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FineTuningMessage {
+    role: Role,
+    content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Vec<ToolsCall>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+/// Writes conversations in the OpenAI chat fine-tuning format, one per line
+pub fn to_fine_tuning_jsonl(conversations: &[Conversation]) -> Result<String, ConversationErrors> {
+    let mut out = String::new();
+    for conversation in conversations {
+        let record = FineTuningRecord {
+            messages: conversation
+                .turns
+                .iter()
+                .map(|turn| FineTuningMessage {
+                    role: turn.message.role.clone(),
+                    content: turn.message.content.clone(),
+                    name: turn.message.name.clone(),
+                    tool_calls: turn.message.tool_calls.clone(),
+                    tool_call_id: turn.message.tool_call_id.clone(),
+                })
+                .collect(),
+        };
+        out.push_str(&serde_json::to_string(&record)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Reads conversations from the OpenAI chat fine-tuning format, using default settings
+pub fn from_fine_tuning_jsonl(jsonl: &str) -> Result<Vec<Conversation>, ConversationErrors> {
+    jsonl
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let record: FineTuningRecord = serde_json::from_str(line)
+                .map_err(|e| ConversationErrors::InvalidRecord(index + 1, e.to_string()))?;
+            let mut conversation = Conversation::new(RequestBody::default());
+            conversation.turns = record
+                .messages
+                .into_iter()
+                .map(|m| {
+                    Turn::from(Message {
+                        tool_calls: m.tool_calls,
+                        tool_call_id: m.tool_call_id,
+                        ..Message::new(m.role, m.content, m.name)
+                    })
+                })
+                .collect();
+            Ok(conversation)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversation::tests::conversation;
+
+    #[test]
+    fn test_markdown() {
+        let markdown = conversation().to_markdown();
+        assert!(markdown.starts_with("# Conversation\n\n**Model:** deepseek-reasoner\n"));
+        assert!(markdown.contains("## System\n\nBe brief.\n"));
+        assert!(markdown.contains("<summary>Reasoning</summary>\n\n15 * 7 = 105\n"));
+        assert!(markdown.contains("105\n\n*Tokens: 10 prompt, 20 completion, 30 total*\n"));
+    }
+
+    #[test]
+    fn test_fine_tuning_round_trip() {
+        let jsonl = to_fine_tuning_jsonl(&[conversation(), conversation()]).unwrap();
+        assert_eq!(jsonl.lines().count(), 2);
+        assert_eq!(
+            jsonl.lines().next().unwrap(),
+            r#"{"messages":[{"role":"system","content":"Be brief."},{"role":"user","content":"What is 15 * 7?"},{"role":"assistant","content":"105"}]}"#
+        );
+        let imported = from_fine_tuning_jsonl(&jsonl).unwrap();
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported[0].messages(), conversation().messages());
+    }
+
+    #[test]
+    fn test_fine_tuning_tool_messages() {
+        let mut conversation = Conversation::default();
+        conversation.push(Message {
+            tool_calls: Some(vec![ToolsCall {
+                id: "call_0".to_string(),
+                type_: "function".to_string(),
+                function_call: crate::types::response::FunctionCall {
+                    name: "weather".to_string(),
+                    arguments: "{}".to_string(),
+                },
+            }]),
+            ..Message::new_assistant_message(String::new())
+        });
+        conversation.push(Message::new_tool_message(
+            "22°C".to_string(),
+            "call_0".to_string(),
+        ));
+        let jsonl = to_fine_tuning_jsonl(&[conversation.clone()]).unwrap();
+        assert!(jsonl.contains(r#""tool_call_id":"call_0""#));
+        let imported = from_fine_tuning_jsonl(&jsonl).unwrap();
+        assert_eq!(imported[0].messages(), conversation.messages());
+        assert!(conversation.to_markdown().contains("## Tool (call_0)"));
+    }
+
+    #[test]
+    fn test_fine_tuning_invalid_line() {
+        let jsonl = "{\"messages\":[]}\n\nnot json\n";
+        assert!(matches!(
+            from_fine_tuning_jsonl(jsonl),
+            Err(ConversationErrors::InvalidRecord(3, _))
+        ));
+    }
+}
@@ -0,0 +1,232 @@
+//! Chat sessions that can be saved, reloaded and exported
+//!
+//! A [`Conversation`] keeps the message history together with the request settings and
+//! the reasoning and `Usage` of every assistant turn.
+
+pub mod export;
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::conversation_errors::ConversationErrors,
+    types::{
+        request::{Message, RequestBody},
+        response::{ChatCompletionsResponse, Usage},
+    },
+};
+
+/// Version written by [`Conversation::to_json`]
+pub const FORMAT_VERSION: u32 = 1;
+
+/// A single message of a conversation
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Turn {
+    pub message: Message,
+    /// Reasoning returned by the reasoner model for assistant turns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
+    /// Usage of the call that produced an assistant turn
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+}
+
+impl From<Message> for Turn {
+    fn from(message: Message) -> Self {
+        Turn {
+            message,
+            reasoning_content: None,
+            usage: None,
+        }
+    }
+}
+
+/// A chat session with its settings and per-turn metadata
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::{conversation::Conversation, request::{Message, Model, RequestBody}};
+///
+/// let mut conversation = Conversation::new(RequestBody::default().with_model(Model::DeepSeekReasoner));
+/// conversation.push(Message::new_user_message("What is 15 * 7?".to_string()));
+/// let json = conversation.to_json().unwrap();
+/// assert_eq!(Conversation::from_json(&json).unwrap(), conversation);
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Conversation {
+    pub version: u32,
+    /// Request settings; its messages are ignored in favour of `turns`
+    pub settings: RequestBody,
+    pub turns: Vec<Turn>,
+}
+
+impl Default for Conversation {
+    fn default() -> Self {
+        Conversation::new(RequestBody::default())
+    }
+}
+
+impl Conversation {
+    /// Starts a conversation with the given settings; any messages in `settings` become
+    /// the first turns
+    pub fn new(settings: RequestBody) -> Self {
+        let turns = settings
+            .messages()
+            .iter()
+            .cloned()
+            .map(Turn::from)
+            .collect();
+        Conversation {
+            version: FORMAT_VERSION,
+            settings: settings.with_messages(Vec::new()),
+            turns,
+        }
+    }
+
+    /// Appends a message
+    pub fn push(&mut self, message: Message) {
+        self.turns.push(Turn::from(message));
+    }
+
+    /// Appends the first choice of `response` with its reasoning and usage
+    pub fn push_response(&mut self, response: &ChatCompletionsResponse) {
+        let Some(choice) = response.choices.first() else {
+            return;
+        };
+        let mut message = Message::from(choice.message.clone());
+        let reasoning_content = message.reasoning_content.take();
+        self.turns.push(Turn {
+            message,
+            reasoning_content,
+            usage: Some(response.usage.clone()),
+        });
+    }
+
+    /// Returns the messages of all turns
+    pub fn messages(&self) -> Vec<Message> {
+        self.turns.iter().map(|turn| turn.message.clone()).collect()
+    }
+
+    /// Builds the request for the next completion
+    pub fn to_request(&self) -> RequestBody {
+        self.settings.clone().with_messages(self.messages())
+    }
+
+    /// Serializes to the versioned JSON format
+    pub fn to_json(&self) -> Result<String, ConversationErrors> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Parses the versioned JSON format
+    pub fn from_json(json: &str) -> Result<Self, ConversationErrors> {
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+        let Version { version } = serde_json::from_str(json)?;
+        if version > FORMAT_VERSION {
+            return Err(ConversationErrors::UnsupportedVersion(version));
+        }
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Writes the versioned JSON format to a file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConversationErrors> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Reads the versioned JSON format from a file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConversationErrors> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        request::{Model, Role, Temperature},
+        response::{ChatCompletionsChoices, FinishReasons, Message as ResponseMessage},
+    };
+
+    pub(crate) fn response(content: &str, reasoning: Option<&str>) -> ChatCompletionsResponse {
+        ChatCompletionsResponse {
+            id: "1".to_string(),
+            choices: vec![ChatCompletionsChoices {
+                finish_reason: FinishReasons::Stop,
+                index: 0,
+                message: ResponseMessage {
+                    content: Some(content.to_string()),
+                    reasoning_content: reasoning.map(str::to_string),
+                    role: Role::Assistant,
+                    tool_calls: None,
+                },
+            }],
+            created: 0,
+            model: "deepseek-reasoner".to_string(),
+            object: "chat.completion".to_string(),
+            usage: Usage {
+                completion_tokens: 20,
+                prompt_tokens: 10,
+                prompt_cache_hit_tokens: 0,
+                prompt_cache_miss_tokens: 10,
+                total_tokens: 30,
+                completion_tokens_details: None,
+                prompt_tokens_details: None,
+            },
+            cache_hit: false,
+        }
+    }
+
+    pub(crate) fn conversation() -> Conversation {
+        let settings = RequestBody::new(
+            vec![Message::new_system_message("Be brief.".to_string())],
+            Model::DeepSeekReasoner,
+        )
+        .with_temperature(Temperature::new(0.0));
+        let mut conversation = Conversation::new(settings);
+        conversation.push(Message::new_user_message("What is 15 * 7?".to_string()));
+        conversation.push_response(&response("105", Some("15 * 7 = 105")));
+        conversation
+    }
+
+    #[test]
+    fn test_turns_and_request() {
+        let conversation = conversation();
+        assert_eq!(conversation.turns.len(), 3);
+        assert!(conversation.settings.messages().is_empty());
+        let assistant = &conversation.turns[2];
+        assert_eq!(assistant.message.content, "105");
+        assert_eq!(assistant.reasoning_content.as_deref(), Some("15 * 7 = 105"));
+        assert_eq!(assistant.usage.as_ref().unwrap().total_tokens, 30);
+
+        let request = conversation.to_request();
+        assert_eq!(request.messages().len(), 3);
+        assert_eq!(request.model(), &Model::DeepSeekReasoner);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let conversation = conversation();
+        let json = conversation.to_json().unwrap();
+        assert_eq!(Conversation::from_json(&json).unwrap(), conversation);
+
+        let future = json.replacen("\"version\": 1", "\"version\": 99", 1);
+        assert!(matches!(
+            Conversation::from_json(&future),
+            Err(ConversationErrors::UnsupportedVersion(99))
+        ));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!("deepseek_conv_{}", std::process::id()));
+        let conversation = conversation();
+        conversation.save(&path).unwrap();
+        let loaded = Conversation::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, conversation);
+    }
+}
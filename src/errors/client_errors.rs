@@ -4,6 +4,41 @@ use thiserror::Error;
 pub enum ClientInitErrors {
     #[error("Error getting API key from environment variable")]
     DeepSeekApiKeyNotSet(#[from] std::env::VarError),
+    #[error("API key contains characters that are not allowed in an HTTP header")]
+    InvalidApiKey,
+    #[error("Error reading API key file: {0}")]
+    KeyFile(#[from] std::io::Error),
+    #[cfg(feature = "dotenv")]
+    #[error("Error loading .env file: {0}")]
+    DotEnv(#[from] dotenvy::Error),
     #[error("Unknown error")]
     Unknown,
 }
+
+/// Clones by value where the source error allows it; I/O errors are recreated from
+/// their kind and message
+impl Clone for ClientInitErrors {
+    fn clone(&self) -> Self {
+        match self {
+            ClientInitErrors::DeepSeekApiKeyNotSet(e) => {
+                ClientInitErrors::DeepSeekApiKeyNotSet(e.clone())
+            }
+            ClientInitErrors::InvalidApiKey => ClientInitErrors::InvalidApiKey,
+            ClientInitErrors::KeyFile(e) => ClientInitErrors::KeyFile(clone_io_error(e)),
+            #[cfg(feature = "dotenv")]
+            ClientInitErrors::DotEnv(e) => ClientInitErrors::DotEnv(match e {
+                dotenvy::Error::LineParse(line, index) => {
+                    dotenvy::Error::LineParse(line.clone(), *index)
+                }
+                dotenvy::Error::EnvVar(e) => dotenvy::Error::EnvVar(e.clone()),
+                dotenvy::Error::Io(e) => dotenvy::Error::Io(clone_io_error(e)),
+                e => dotenvy::Error::Io(std::io::Error::other(e.to_string())),
+            }),
+            ClientInitErrors::Unknown => ClientInitErrors::Unknown,
+        }
+    }
+}
+
+fn clone_io_error(error: &std::io::Error) -> std::io::Error {
+    std::io::Error::new(error.kind(), error.to_string())
+}
@@ -0,0 +1,16 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ConversationErrors {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Unsupported conversation format version {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("Invalid fine-tuning record on line {0}: {1}")]
+    InvalidRecord(usize, String),
+}
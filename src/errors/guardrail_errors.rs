@@ -0,0 +1,49 @@
+use std::fmt;
+
+use thiserror::Error;
+
+use super::request_errors::RequestErrors;
+use crate::types::response::{ChatCompletionsResponse, FinishReasons};
+
+/// A validator's objection to a reply
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub validator: String,
+    pub reason: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.validator, self.reason)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum GuardrailErrors {
+    #[error(transparent)]
+    Request(#[from] RequestErrors),
+
+    /// The last reply still failed validation after every retry
+    #[error("Reply rejected after {attempts} attempt(s): {}", join(.violations))]
+    Rejected {
+        attempts: usize,
+        violations: Vec<Violation>,
+        /// The rejected response, which must not be shown to users as is
+        response: Box<ChatCompletionsResponse>,
+    },
+
+    /// The reply ended with a finish reason configured as a failure
+    #[error("Reply ended with finish reason {reason:?}")]
+    FinishReason {
+        reason: FinishReasons,
+        response: Box<ChatCompletionsResponse>,
+    },
+}
+
+fn join(violations: &[Violation]) -> String {
+    violations
+        .iter()
+        .map(Violation::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
@@ -0,0 +1,9 @@
+use thiserror::Error;
+
+use crate::types::request::Role;
+
+#[derive(Debug, Error)]
+pub enum MessageErrors {
+    #[error("Only assistant messages can be converted into a response message, got {0:?}")]
+    NotAnAssistantMessage(Role),
+}
@@ -1,2 +1,7 @@
 pub mod client_errors;
+pub mod conversation_errors;
+pub mod eval_errors;
+pub mod guardrail_errors;
+pub mod message_errors;
 pub mod request_errors;
+pub mod template_errors;
@@ -1,10 +1,20 @@
-use reqwest::{Error as ReqwestError, StatusCode};
+#[cfg(feature = "reqwest")]
+use std::sync::Arc;
+
+use http::StatusCode;
+#[cfg(feature = "reqwest")]
+use reqwest::Error as ReqwestError;
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+use super::client_errors::ClientInitErrors;
+
+#[derive(Debug, Clone, Error)]
 pub enum RequestErrors {
+    /// An HTTP failure that fits no other variant; the error is shared so that
+    /// `RequestErrors` can be cloned
+    #[cfg(feature = "reqwest")]
     #[error("HTTP Error: {0}")]
-    HttpError(ReqwestError), // Removed #[from]
+    HttpError(Arc<ReqwestError>),
 
     #[error("Connection error: {0}")]
     ConnectionError(String),
@@ -39,18 +49,80 @@ pub enum RequestErrors {
     #[error("Rate limit exceeded: {0}")]
     RateLimitExceeded(String),
 
+    #[error("Insufficient balance: {0}")]
+    InsufficientBalance(String),
+
+    #[error("No API key available: {0}")]
+    NoApiKeyAvailable(String),
+
+    #[error("Client error: {0}")]
+    ClientInit(#[from] ClientInitErrors),
+
+    #[error("Stream interrupted ({reason}) after {} characters", content.chars().count())]
+    StreamInterrupted {
+        reason: InterruptReason,
+        /// Content received before the interruption
+        content: String,
+        /// Reasoning received before the interruption
+        reasoning_content: String,
+        /// Whether the connection was shut down deliberately, by the caller or the
+        /// server, rather than stalling
+        closed_cleanly: bool,
+    },
+
+    #[error("Circuit open: {0}")]
+    CircuitOpen(String),
+
     #[error("Status {0}: {1}")]
     StatusError(StatusCode, String),
     #[error("Unknown error")]
     Unknown,
 }
 
+/// Why a streamed response stopped before it finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptReason {
+    /// The caller's cancellation token fired
+    Cancelled,
+    /// No chunk arrived within the client's stream idle timeout
+    IdleTimeout,
+    /// The connection ended without `[DONE]`, either closed by the server or broken
+    /// by a transport error
+    ConnectionClosed,
+}
+
+impl std::fmt::Display for InterruptReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            InterruptReason::Cancelled => "cancelled",
+            InterruptReason::IdleTimeout => "idle timeout",
+            InterruptReason::ConnectionClosed => "connection closed",
+        })
+    }
+}
+
+#[cfg(feature = "client")]
+impl RequestErrors {
+    pub(crate) fn interrupted(reason: InterruptReason, closed_cleanly: bool) -> Self {
+        RequestErrors::StreamInterrupted {
+            reason,
+            content: String::new(),
+            reasoning_content: String::new(),
+            closed_cleanly,
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
 impl From<ReqwestError> for RequestErrors {
     fn from(error: ReqwestError) -> Self {
         if let Some(status) = error.status() {
             match status {
                 StatusCode::BAD_REQUEST => RequestErrors::BadRequest(error.to_string()),
                 StatusCode::UNAUTHORIZED => RequestErrors::Unauthorized(error.to_string()),
+                StatusCode::PAYMENT_REQUIRED => {
+                    RequestErrors::InsufficientBalance(error.to_string())
+                }
                 StatusCode::FORBIDDEN => RequestErrors::Forbidden,
                 StatusCode::TOO_MANY_REQUESTS => {
                     RequestErrors::RateLimitExceeded(error.to_string())
@@ -70,7 +142,7 @@ impl From<ReqwestError> for RequestErrors {
         } else if error.is_decode() {
             RequestErrors::DecodeError(error.to_string())
         } else {
-            RequestErrors::HttpError(error)
+            RequestErrors::HttpError(Arc::new(error))
         }
     }
 }
@@ -0,0 +1,16 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TemplateErrors {
+    #[error("Error reading template file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Missing template variables: {}", .0.join(", "))]
+    MissingVariables(Vec<String>),
+
+    #[error("Unexpected template variables: {}", .0.join(", "))]
+    UnexpectedVariables(Vec<String>),
+
+    #[error("Template variables must serialize to a JSON object: {0}")]
+    InvalidVariables(String),
+}
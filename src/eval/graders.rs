@@ -0,0 +1,420 @@
+//! Scoring model outputs against eval cases
+
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::EvalCase;
+use crate::{
+    client::provider::ChatProvider,
+    schema::{self, strip_code_fence},
+    types::request::{Message, RequestBody},
+};
+
+/// The verdict of a [`Grader`] on one output
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Grade {
+    /// Name of the grader, filled in by the eval run
+    pub grader: String,
+    pub passed: bool,
+    /// From 0 to 1
+    pub score: f64,
+    pub reason: Option<String>,
+}
+
+impl Grade {
+    pub fn pass() -> Self {
+        Grade {
+            grader: String::new(),
+            passed: true,
+            score: 1.0,
+            reason: None,
+        }
+    }
+
+    pub fn fail(reason: impl Into<String>) -> Self {
+        Grade {
+            grader: String::new(),
+            passed: false,
+            score: 0.0,
+            reason: Some(reason.into()),
+        }
+    }
+}
+
+/// Scores the output of a model for a case
+pub trait Grader: Send + Sync {
+    /// Short name for reports
+    fn name(&self) -> &str;
+    fn grade<'a>(&'a self, case: &'a EvalCase, output: &'a str) -> BoxFuture<'a, Grade>;
+}
+
+fn expected_text(case: &EvalCase) -> Result<String, Grade> {
+    case.expected_text()
+        .ok_or_else(|| Grade::fail("the case has no expected answer"))
+}
+
+/// Passes when the trimmed output equals the case's expected answer
+#[derive(Debug, Clone, Default)]
+pub struct ExactMatch {
+    ignore_case: bool,
+}
+
+impl ExactMatch {
+    pub fn new() -> Self {
+        ExactMatch::default()
+    }
+
+    pub fn ignore_case(mut self) -> Self {
+        self.ignore_case = true;
+        self
+    }
+}
+
+impl Grader for ExactMatch {
+    fn name(&self) -> &str {
+        "exact_match"
+    }
+
+    fn grade<'a>(&'a self, case: &'a EvalCase, output: &'a str) -> BoxFuture<'a, Grade> {
+        let grade = match expected_text(case) {
+            Err(grade) => grade,
+            Ok(expected) => {
+                let (output, expected) = (output.trim(), expected.trim());
+                let equal = if self.ignore_case {
+                    output.to_lowercase() == expected.to_lowercase()
+                } else {
+                    output == expected
+                };
+                if equal {
+                    Grade::pass()
+                } else {
+                    Grade::fail(format!("expected `{}`", expected))
+                }
+            }
+        };
+        Box::pin(async move { grade })
+    }
+}
+
+/// Passes when the output matches a regular expression, either a fixed one or the
+/// case's expected answer
+#[derive(Debug, Clone)]
+pub struct RegexMatch {
+    regex: Option<Regex>,
+}
+
+impl RegexMatch {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(RegexMatch {
+            regex: Some(Regex::new(pattern)?),
+        })
+    }
+
+    /// Uses each case's expected answer as the pattern
+    pub fn expected() -> Self {
+        RegexMatch { regex: None }
+    }
+}
+
+impl Grader for RegexMatch {
+    fn name(&self) -> &str {
+        "regex"
+    }
+
+    fn grade<'a>(&'a self, case: &'a EvalCase, output: &'a str) -> BoxFuture<'a, Grade> {
+        let regex = match &self.regex {
+            Some(regex) => Ok(regex.clone()),
+            None => expected_text(case).and_then(|pattern| {
+                Regex::new(&pattern).map_err(|e| Grade::fail(format!("invalid pattern: {}", e)))
+            }),
+        };
+        let grade = match regex {
+            Err(grade) => grade,
+            Ok(regex) if regex.is_match(output) => Grade::pass(),
+            Ok(regex) => Grade::fail(format!("doesn't match `{}`", regex)),
+        };
+        Box::pin(async move { grade })
+    }
+}
+
+/// Passes when the output is JSON valid against a schema; see [`schema::validate`]
+/// for the supported keywords
+///
+/// A Markdown code fence around the JSON is ignored.
+#[derive(Debug, Clone)]
+pub struct JsonSchema {
+    schema: Value,
+}
+
+impl JsonSchema {
+    pub fn new(schema: Value) -> Self {
+        JsonSchema { schema }
+    }
+}
+
+impl Grader for JsonSchema {
+    fn name(&self) -> &str {
+        "json_schema"
+    }
+
+    fn grade<'a>(&'a self, _case: &'a EvalCase, output: &'a str) -> BoxFuture<'a, Grade> {
+        let grade = match serde_json::from_str::<Value>(strip_code_fence(output)) {
+            Err(e) => Grade::fail(format!("not JSON: {}", e)),
+            Ok(value) => match schema::validate(&self.schema, &value) {
+                Ok(()) => Grade::pass(),
+                Err(errors) => Grade::fail(errors.join("; ")),
+            },
+        };
+        Box::pin(async move { grade })
+    }
+}
+
+/// Passes when the last number in the output is within `tolerance` of the expected
+/// number
+#[derive(Debug, Clone, Copy)]
+pub struct NumericTolerance {
+    tolerance: f64,
+}
+
+impl NumericTolerance {
+    pub fn new(tolerance: f64) -> Self {
+        NumericTolerance {
+            tolerance: tolerance.abs(),
+        }
+    }
+}
+
+/// Returns the last number in `text`, ignoring thousands separators
+fn last_number(text: &str) -> Option<f64> {
+    let cleaned = text.replace(',', "");
+    let mut last = None;
+    let mut current = String::new();
+    let mut previous = ' ';
+    for c in cleaned.chars().chain([' ']) {
+        // A minus sign right after a word or number is a dash, as in "10-20"
+        let continues = c.is_ascii_digit()
+            || (c == '.' && !current.contains('.') && !current.is_empty())
+            || (c == '-' && current.is_empty() && !previous.is_alphanumeric());
+        previous = c;
+        if continues {
+            current.push(c);
+            continue;
+        }
+        if let Ok(number) = current.trim_end_matches('.').parse::<f64>() {
+            last = Some(number);
+        }
+        current.clear();
+    }
+    last
+}
+
+impl Grader for NumericTolerance {
+    fn name(&self) -> &str {
+        "numeric"
+    }
+
+    fn grade<'a>(&'a self, case: &'a EvalCase, output: &'a str) -> BoxFuture<'a, Grade> {
+        let expected = match &case.expected {
+            Some(Value::Number(n)) => n.as_f64(),
+            Some(Value::String(s)) => s.trim().parse().ok(),
+            _ => None,
+        };
+        let grade = match (expected, last_number(output)) {
+            (None, _) => Grade::fail("the case has no expected number"),
+            (_, None) => Grade::fail("no number in the output"),
+            (Some(expected), Some(actual)) if (expected - actual).abs() <= self.tolerance => {
+                Grade::pass()
+            }
+            (Some(expected), Some(actual)) => {
+                Grade::fail(format!("expected {}, got {}", expected, actual))
+            }
+        };
+        Box::pin(async move { grade })
+    }
+}
+
+const JUDGE_PROMPT: &str = "You grade answers. Compare the answer with the reference \
+answer and the criteria. Reply with PASS or FAIL on the first line, then one sentence \
+explaining why.";
+
+/// Asks a model whether the output is correct
+///
+/// The judge sees the case's last user message, its expected answer if any, and the
+/// optional criteria. Calls that fail count as failed grades. The judge's token usage
+/// isn't included in the report.
+#[derive(Clone)]
+pub struct ModelJudge {
+    provider: Arc<dyn ChatProvider>,
+    settings: RequestBody,
+    criteria: Option<String>,
+}
+
+impl ModelJudge {
+    pub fn new(provider: impl ChatProvider + 'static) -> Self {
+        ModelJudge {
+            provider: Arc::new(provider),
+            settings: RequestBody::default(),
+            criteria: None,
+        }
+    }
+
+    /// Sets the judge's model and sampling settings; messages are ignored
+    pub fn with_settings(mut self, settings: RequestBody) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Describes what a passing answer looks like
+    pub fn with_criteria(mut self, criteria: impl Into<String>) -> Self {
+        self.criteria = Some(criteria.into());
+        self
+    }
+
+    fn prompt(&self, case: &EvalCase, output: &str) -> String {
+        let question = case
+            .messages()
+            .last()
+            .map(|message| message.content.clone())
+            .unwrap_or_default();
+        let mut prompt = format!("Question:\n{}\n", question);
+        if let Some(expected) = case.expected_text() {
+            prompt.push_str(&format!("\nReference answer:\n{}\n", expected));
+        }
+        if let Some(criteria) = &self.criteria {
+            prompt.push_str(&format!("\nCriteria:\n{}\n", criteria));
+        }
+        prompt.push_str(&format!("\nAnswer to grade:\n{}", output));
+        prompt
+    }
+}
+
+impl Grader for ModelJudge {
+    fn name(&self) -> &str {
+        "model_judge"
+    }
+
+    fn grade<'a>(&'a self, case: &'a EvalCase, output: &'a str) -> BoxFuture<'a, Grade> {
+        let request = self.settings.clone().with_messages(vec![
+            Message::new_system_message(JUDGE_PROMPT.to_string()),
+            Message::new_user_message(self.prompt(case, output)),
+        ]);
+        Box::pin(async move {
+            let response = match self.provider.chat_completions(request).await {
+                Ok(response) => response,
+                Err(e) => return Grade::fail(format!("judge failed: {}", e)),
+            };
+            let verdict = response
+                .choices
+                .first()
+                .and_then(|choice| choice.message.content.clone())
+                .unwrap_or_default();
+            verdict_grade(&verdict)
+        })
+    }
+}
+
+/// Reads a judge's reply: the first word of the first line must be PASS, ignoring
+/// Markdown emphasis and case; anything else fails
+fn verdict_grade(verdict: &str) -> Grade {
+    let verdict = verdict.trim();
+    let (first, rest) = verdict.split_once('\n').unwrap_or((verdict, ""));
+    let reason = Some(rest.trim().to_string()).filter(|r| !r.is_empty());
+    let word = first
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default();
+    if word.eq_ignore_ascii_case("PASS") {
+        Grade {
+            reason,
+            ..Grade::pass()
+        }
+    } else {
+        Grade {
+            reason: reason.or(Some(first.to_string())),
+            ..Grade::fail("")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn case(expected: Value) -> EvalCase {
+        EvalCase::new("1", "question").with_expected(expected)
+    }
+
+    async fn passes(grader: &dyn Grader, case: &EvalCase, output: &str) -> bool {
+        grader.grade(case, output).await.passed
+    }
+
+    #[tokio::test]
+    async fn test_exact_and_regex() {
+        let paris = case(json!("Paris"));
+        assert!(passes(&ExactMatch::new(), &paris, " Paris\n").await);
+        assert!(!passes(&ExactMatch::new(), &paris, "paris").await);
+        assert!(passes(&ExactMatch::new().ignore_case(), &paris, "paris").await);
+        assert!(!passes(&ExactMatch::new(), &EvalCase::new("2", "q"), "x").await);
+
+        let regex = RegexMatch::new(r"^\d{4}-\d{2}-\d{2}$").unwrap();
+        assert!(passes(&regex, &paris, "2024-01-31").await);
+        assert!(!passes(&regex, &paris, "Jan 31").await);
+        assert!(
+            passes(
+                &RegexMatch::expected(),
+                &case(json!("(?i)paris")),
+                "It's PARIS"
+            )
+            .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_json_schema() {
+        let grader = JsonSchema::new(json!({ "type": "object", "required": ["a"] }));
+        let any = EvalCase::new("1", "q");
+        assert!(passes(&grader, &any, "```json\n{\"a\": 1}\n```").await);
+        let grade = grader.grade(&any, "{\"b\": 1}").await;
+        assert_eq!(
+            grade.reason.as_deref(),
+            Some("$: missing required property `a`")
+        );
+        assert!(!passes(&grader, &any, "nope").await);
+    }
+
+    #[tokio::test]
+    async fn test_numeric() {
+        let grader = NumericTolerance::new(0.01);
+        assert!(passes(&grader, &case(json!(105)), "15 * 7 = 105.").await);
+        assert!(passes(&grader, &case(json!("1234.5")), "About 1,234.499").await);
+        assert!(passes(&grader, &case(json!(-3)), "It drops to -3").await);
+        assert!(!passes(&grader, &case(json!(2)), "1 or 3").await);
+        assert!(!passes(&grader, &case(json!(2)), "two").await);
+        assert_eq!(last_number("v1.2.3 - 4"), Some(4.0));
+        assert_eq!(last_number("10-20"), Some(20.0));
+        assert_eq!(last_number("on 2024-01-05"), Some(5.0));
+        assert_eq!(last_number("from 5 to -2.5"), Some(-2.5));
+    }
+
+    #[test]
+    fn test_verdict() {
+        assert!(verdict_grade("PASS\nCorrect total.").passed);
+        assert!(verdict_grade("**Pass**: matches").passed);
+        let fail = verdict_grade("FAIL – the answer does not pass the criteria");
+        assert!(!fail.passed);
+        assert_eq!(
+            fail.reason.as_deref(),
+            Some("FAIL – the answer does not pass the criteria")
+        );
+        assert!(!verdict_grade("NOT PASS").passed);
+        assert!(!verdict_grade("PASSABLE").passed);
+        assert!(!verdict_grade("").passed);
+    }
+}
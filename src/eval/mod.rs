@@ -0,0 +1,501 @@
+//! Comparing prompts and models on datasets
+//!
+//! An [`Eval`] runs every [`EvalCase`] through each [`Variant`] of a request, scores
+//! the outputs with [`Grader`](graders::Grader)s and collects accuracy, latency, token
+//! usage and cost per variant in an [`EvalReport`].
+
+pub mod graders;
+pub mod pricing;
+
+use std::{
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use graders::{Grade, Grader};
+use pricing::Pricing;
+
+use crate::{
+    client::provider::ChatProvider,
+    errors::eval_errors::EvalErrors,
+    types::{
+        request::{Message, Model, RequestBody},
+        response::Usage,
+    },
+};
+
+/// One input of a dataset with its expected answer
+///
+/// In JSONL, each line holds an object with an optional `id`, either `input` (a user
+/// message) or `messages`, and an optional `expected` value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalCase {
+    /// Defaults to the line number when loaded from JSONL
+    #[serde(default)]
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<Message>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected: Option<Value>,
+}
+
+impl EvalCase {
+    pub fn new(id: impl Into<String>, input: impl Into<String>) -> Self {
+        EvalCase {
+            id: id.into(),
+            input: Some(input.into()),
+            messages: Vec::new(),
+            expected: None,
+        }
+    }
+
+    pub fn with_expected(mut self, expected: Value) -> Self {
+        self.expected = Some(expected);
+        self
+    }
+
+    /// Returns the case's messages followed by its input as a user message
+    pub fn messages(&self) -> Vec<Message> {
+        let mut messages = self.messages.clone();
+        if let Some(input) = &self.input {
+            messages.push(Message::new_user_message(input.clone()));
+        }
+        messages
+    }
+
+    /// Returns the expected answer as text; strings are returned as is, other values
+    /// as JSON
+    pub fn expected_text(&self) -> Option<String> {
+        match self.expected.as_ref()? {
+            Value::String(text) => Some(text.clone()),
+            value => Some(value.to_string()),
+        }
+    }
+}
+
+/// Parses eval cases from JSONL, skipping blank lines
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::eval::parse_cases;
+///
+/// let cases = parse_cases(r#"{"input": "What is 15 * 7?", "expected": 105}"#).unwrap();
+/// assert_eq!(cases[0].id, "1");
+/// assert_eq!(cases[0].expected_text().as_deref(), Some("105"));
+/// ```
+pub fn parse_cases(jsonl: &str) -> Result<Vec<EvalCase>, EvalErrors> {
+    jsonl
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let mut case: EvalCase = serde_json::from_str(line)
+                .map_err(|e| EvalErrors::InvalidCase(index + 1, e.to_string()))?;
+            if case.input.is_none() && case.messages.is_empty() {
+                return Err(EvalErrors::InvalidCase(
+                    index + 1,
+                    "expected `input` or `messages`".to_string(),
+                ));
+            }
+            if case.id.is_empty() {
+                case.id = (index + 1).to_string();
+            }
+            Ok(case)
+        })
+        .collect()
+}
+
+/// Reads eval cases from a JSONL file
+pub fn load_cases(path: impl AsRef<Path>) -> Result<Vec<EvalCase>, EvalErrors> {
+    parse_cases(&std::fs::read_to_string(path)?)
+}
+
+/// A named request configuration to evaluate
+///
+/// The case's messages are appended to the messages of `settings`, so a variant can
+/// carry its own system prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub name: String,
+    pub settings: RequestBody,
+}
+
+impl Variant {
+    pub fn new(name: impl Into<String>, settings: RequestBody) -> Self {
+        Variant {
+            name: name.into(),
+            settings,
+        }
+    }
+}
+
+/// The outcome of one case under one variant
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CaseResult {
+    pub case_id: String,
+    pub variant: String,
+    pub output: Option<String>,
+    /// The request error, when the call failed
+    pub error: Option<String>,
+    pub grades: Vec<Grade>,
+    /// Whether the call succeeded and every grader passed
+    pub passed: bool,
+    pub latency_ms: f64,
+    pub usage: Usage,
+}
+
+/// Totals for one variant
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VariantSummary {
+    pub name: String,
+    pub model: String,
+    pub cases: usize,
+    pub passed: usize,
+    pub errors: usize,
+    /// Share of cases passed, from 0 to 1
+    pub accuracy: f64,
+    pub mean_latency_ms: f64,
+    pub p95_latency_ms: f64,
+    pub usage: Usage,
+    /// Estimated cost in US dollars
+    pub cost_usd: f64,
+}
+
+/// The results of an [`Eval`] run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EvalReport {
+    pub variants: Vec<VariantSummary>,
+    pub results: Vec<CaseResult>,
+}
+
+impl EvalReport {
+    /// Renders the per-variant summary as a Markdown table
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from(
+            "| Variant | Model | Accuracy | Passed | Errors | Mean latency | p95 latency | Tokens | Cost |\n\
+             | --- | --- | --- | --- | --- | --- | --- | --- | --- |\n",
+        );
+        for v in &self.variants {
+            out.push_str(&format!(
+                "| {} | {} | {:.1}% | {}/{} | {} | {:.0} ms | {:.0} ms | {} | ${:.4} |\n",
+                v.name,
+                v.model,
+                v.accuracy * 100.0,
+                v.passed,
+                v.cases,
+                v.errors,
+                v.mean_latency_ms,
+                v.p95_latency_ms,
+                v.usage.total_tokens,
+                v.cost_usd
+            ));
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> Result<String, EvalErrors> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Writes the report as JSON
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), EvalErrors> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+}
+
+/// Runs datasets through request variants and grades the outputs
+///
+/// # Example
+/// ```no_run
+/// use clia_deepseek_rs::{
+///     eval::{graders::NumericTolerance, load_cases, Eval, Variant},
+///     request::{Model, RequestBody},
+///     DeepSeekClient,
+/// };
+/// # #[tokio::main]
+/// # async fn main() {
+/// let cases = load_cases("math.jsonl").unwrap();
+/// let report = Eval::new(DeepSeekClient::default().unwrap())
+///     .with_variant(Variant::new("chat", RequestBody::default()))
+///     .with_variant(Variant::new(
+///         "reasoner",
+///         RequestBody::default().with_model(Model::DeepSeekReasoner),
+///     ))
+///     .with_grader(NumericTolerance::new(0.001))
+///     .run(&cases)
+///     .await;
+/// println!("{}", report.to_markdown());
+/// # }
+/// ```
+pub struct Eval {
+    provider: Arc<dyn ChatProvider>,
+    variants: Vec<Variant>,
+    graders: Vec<Arc<dyn Grader>>,
+    pricing: Vec<(Model, Pricing)>,
+    concurrency: usize,
+}
+
+impl Eval {
+    /// Runs 4 calls at once with DeepSeek's list prices
+    pub fn new(provider: impl ChatProvider + 'static) -> Self {
+        Eval {
+            provider: Arc::new(provider),
+            variants: Vec::new(),
+            graders: Vec::new(),
+            pricing: Vec::new(),
+            concurrency: 4,
+        }
+    }
+
+    pub fn with_variant(mut self, variant: Variant) -> Self {
+        self.variants.push(variant);
+        self
+    }
+
+    /// Adds a grader; a case passes when every grader passes it
+    pub fn with_grader(mut self, grader: impl Grader + 'static) -> Self {
+        self.graders.push(Arc::new(grader));
+        self
+    }
+
+    /// Overrides the prices used for `model`
+    pub fn with_pricing(mut self, model: Model, pricing: Pricing) -> Self {
+        self.pricing.retain(|(m, _)| *m != model);
+        self.pricing.push((model, pricing));
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    fn pricing(&self, model: &Model) -> Pricing {
+        self.pricing
+            .iter()
+            .find(|(m, _)| m == model)
+            .map(|(_, pricing)| *pricing)
+            .unwrap_or_else(|| Pricing::for_model(model))
+    }
+
+    /// Runs every case through every variant
+    ///
+    /// Failed calls are recorded as failed cases rather than stopping the run.
+    pub async fn run(&self, cases: &[EvalCase]) -> EvalReport {
+        let jobs = self
+            .variants
+            .iter()
+            .flat_map(|variant| cases.iter().map(move |case| (variant, case)));
+        let results: Vec<CaseResult> = stream::iter(jobs)
+            .map(|(variant, case)| self.run_case(variant, case))
+            .buffered(self.concurrency)
+            .collect()
+            .await;
+        let variants = self
+            .variants
+            .iter()
+            .map(|variant| self.summarize(variant, &results))
+            .collect();
+        EvalReport { variants, results }
+    }
+
+    async fn run_case(&self, variant: &Variant, case: &EvalCase) -> CaseResult {
+        let mut messages = variant.settings.messages().to_vec();
+        messages.extend(case.messages());
+        let request = variant.settings.clone().with_messages(messages);
+        let started = Instant::now();
+        let result = self.provider.chat_completions(request).await;
+        let latency = started.elapsed();
+        let mut case_result = CaseResult {
+            case_id: case.id.clone(),
+            variant: variant.name.clone(),
+            output: None,
+            error: None,
+            grades: Vec::new(),
+            passed: false,
+            latency_ms: latency_ms(latency),
+            usage: Usage::default(),
+        };
+        let response = match result {
+            Ok(response) => response,
+            Err(e) => {
+                case_result.error = Some(e.to_string());
+                return case_result;
+            }
+        };
+        let output = response
+            .choices
+            .first()
+            .and_then(|choice| choice.message.content.clone())
+            .unwrap_or_default();
+        for grader in &self.graders {
+            let mut grade = grader.grade(case, &output).await;
+            grade.grader = grader.name().to_string();
+            case_result.grades.push(grade);
+        }
+        case_result.passed = case_result.grades.iter().all(|grade| grade.passed);
+        case_result.output = Some(output);
+        case_result.usage = response.usage;
+        case_result
+    }
+
+    fn summarize(&self, variant: &Variant, results: &[CaseResult]) -> VariantSummary {
+        let results: Vec<&CaseResult> = results
+            .iter()
+            .filter(|result| result.variant == variant.name)
+            .collect();
+        let mut usage = Usage::default();
+        for result in &results {
+            usage += &result.usage;
+        }
+        let mut latencies: Vec<f64> = results.iter().map(|result| result.latency_ms).collect();
+        latencies.sort_by(f64::total_cmp);
+        let count = results.len();
+        let passed = results.iter().filter(|result| result.passed).count();
+        let model = variant.settings.model();
+        VariantSummary {
+            name: variant.name.clone(),
+            model: model.to_string(),
+            cases: count,
+            passed,
+            errors: results
+                .iter()
+                .filter(|result| result.error.is_some())
+                .count(),
+            accuracy: if count == 0 {
+                0.0
+            } else {
+                passed as f64 / count as f64
+            },
+            mean_latency_ms: if count == 0 {
+                0.0
+            } else {
+                latencies.iter().sum::<f64>() / count as f64
+            },
+            p95_latency_ms: percentile(&latencies, 0.95),
+            cost_usd: self.pricing(model).cost(&usage),
+            usage,
+        }
+    }
+}
+
+fn latency_ms(latency: Duration) -> f64 {
+    latency.as_secs_f64() * 1000.0
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[f64], fraction: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (fraction * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{graders::ExactMatch, *};
+    use crate::{
+        errors::request_errors::RequestErrors,
+        testing::{response, FakeProvider},
+    };
+
+    /// Answers "4" with the chat model and "5" with the reasoner; fails on "boom"
+    fn arithmetic() -> FakeProvider {
+        FakeProvider::new(|request| {
+            if request.messages().last().unwrap().content == "boom" {
+                return Err(RequestErrors::Unknown);
+            }
+            let mut response = response(match request.model() {
+                Model::DeepseekChat => "4",
+                Model::DeepSeekReasoner => "5",
+            });
+            response.usage.completion_tokens = 1000;
+            response.usage.prompt_tokens = 2000;
+            response.usage.prompt_cache_hit_tokens = 1000;
+            response.usage.prompt_cache_miss_tokens = 1000;
+            response.usage.total_tokens = 3000;
+            Ok(response)
+        })
+    }
+
+    #[test]
+    fn test_parse_cases() {
+        let jsonl = r#"{"id": "a", "input": "2+2", "expected": "4"}
+
+{"messages": [{"role": "user", "content": "hi"}]}"#;
+        let cases = parse_cases(jsonl).unwrap();
+        assert_eq!(cases[0].id, "a");
+        assert_eq!(cases[1].id, "3");
+        assert_eq!(cases[1].messages().len(), 1);
+        assert!(matches!(
+            parse_cases("{\"expected\": 1}"),
+            Err(EvalErrors::InvalidCase(1, _))
+        ));
+        assert!(matches!(
+            parse_cases("\nnot json"),
+            Err(EvalErrors::InvalidCase(2, _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        let cases = vec![
+            EvalCase::new("1", "2+2").with_expected(json!("4")),
+            EvalCase::new("2", "1+3").with_expected(json!("4")),
+            EvalCase::new("3", "boom").with_expected(json!("4")),
+        ];
+        let report = Eval::new(arithmetic())
+            .with_variant(Variant::new("chat", RequestBody::default()))
+            .with_variant(Variant::new(
+                "reasoner",
+                RequestBody::default().with_model(Model::DeepSeekReasoner),
+            ))
+            .with_grader(ExactMatch::new())
+            .with_pricing(
+                Model::DeepSeekReasoner,
+                Pricing {
+                    cache_hit_input: 1.0,
+                    cache_miss_input: 2.0,
+                    output: 3.0,
+                },
+            )
+            .run(&cases)
+            .await;
+
+        assert_eq!(report.results.len(), 6);
+        let chat = &report.variants[0];
+        assert_eq!((chat.cases, chat.passed, chat.errors), (3, 2, 1));
+        assert!((chat.accuracy - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(chat.usage.total_tokens, 6000);
+        assert!(
+            (chat.cost_usd - (2000.0 * 0.07 + 2000.0 * 0.27 + 2000.0 * 1.10) / 1e6).abs() < 1e-12
+        );
+        let reasoner = &report.variants[1];
+        assert_eq!(reasoner.passed, 0);
+        assert!((reasoner.cost_usd - 0.012).abs() < 1e-12);
+        assert_eq!(report.results[3].grades[0].grader, "exact_match");
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("| chat | deepseek-chat | 66.7% | 2/3 | 1 |"));
+        let json: Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["variants"][1]["name"], "reasoner");
+    }
+
+    #[test]
+    fn test_percentile() {
+        let values: Vec<f64> = (1..=20).map(f64::from).collect();
+        assert_eq!(percentile(&values, 0.95), 19.0);
+        assert_eq!(percentile(&[], 0.95), 0.0);
+    }
+}
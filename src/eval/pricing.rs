@@ -0,0 +1,68 @@
+//! Token prices for estimating the cost of calls
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{request::Model, response::Usage};
+
+/// Prices in US dollars per million tokens
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::{eval::pricing::Pricing, request::Model, types::response::Usage};
+///
+/// let usage = Usage {
+///     prompt_tokens: 1_000_000,
+///     prompt_cache_miss_tokens: 1_000_000,
+///     completion_tokens: 1_000_000,
+///     total_tokens: 2_000_000,
+///     ..Usage::default()
+/// };
+/// let cost = Pricing::for_model(&Model::DeepseekChat).cost(&usage);
+/// assert!((cost - 1.37).abs() < 1e-9);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Pricing {
+    /// Prompt tokens served from DeepSeek's context cache
+    pub cache_hit_input: f64,
+    pub cache_miss_input: f64,
+    pub output: f64,
+}
+
+impl Pricing {
+    /// Returns DeepSeek's standard list prices for `model`
+    ///
+    /// Prices change and off-peak discounts aren't included; set current prices with
+    /// `Eval::with_pricing` where the numbers matter.
+    pub fn for_model(model: &Model) -> Self {
+        match model {
+            Model::DeepseekChat => Pricing {
+                cache_hit_input: 0.07,
+                cache_miss_input: 0.27,
+                output: 1.10,
+            },
+            Model::DeepSeekReasoner => Pricing {
+                cache_hit_input: 0.14,
+                cache_miss_input: 0.55,
+                output: 2.19,
+            },
+        }
+    }
+
+    /// Returns the cost of `usage` in US dollars
+    ///
+    /// Servers that don't report cache hits are charged the cache-miss price for all
+    /// prompt tokens.
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        let (hit, miss) = match (
+            usage.prompt_cache_hit_tokens,
+            usage.prompt_cache_miss_tokens,
+        ) {
+            (0, 0) => (0, usage.prompt_tokens),
+            counts => counts,
+        };
+        (f64::from(hit) * self.cache_hit_input
+            + f64::from(miss) * self.cache_miss_input
+            + f64::from(usage.completion_tokens) * self.output)
+            / 1_000_000.0
+    }
+}
@@ -0,0 +1,309 @@
+//! Validating replies before they reach users
+//!
+//! A [`Guardrail`] sends a request through a [`ChatProvider`], rejects replies that end
+//! with a failing finish reason, runs [`Validator`]s on the reply's content and can ask
+//! the model to try again with the problems appended to the conversation.
+
+pub mod validators;
+
+use std::sync::Arc;
+
+use validators::Validator;
+
+pub use crate::errors::guardrail_errors::{GuardrailErrors, Violation};
+use crate::{
+    client::provider::ChatProvider,
+    types::{
+        request::{Message, RequestBody},
+        response::{ChatCompletionsResponse, FinishReasons, Usage},
+    },
+};
+
+type Feedback = dyn Fn(&[Violation]) -> String + Send + Sync;
+
+/// A reply that passed every validator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuardedResponse {
+    pub response: ChatCompletionsResponse,
+    /// Number of calls made, including rejected ones
+    pub attempts: usize,
+    /// Usage of all attempts together
+    pub usage: Usage,
+}
+
+/// Runs chat completions through validators, retrying with feedback on failure
+///
+/// Replies ending with [`FinishReasons::ContentFilter`] or [`FinishReasons::Length`]
+/// fail by default with [`GuardrailErrors::FinishReason`]; these are not retried.
+///
+/// # Example
+/// ```no_run
+/// use clia_deepseek_rs::{
+///     guardrails::{
+///         validators::{LengthLimit, RegexRule, SecretLeak},
+///         Guardrail,
+///     },
+///     request::{Message, RequestBody},
+///     DeepSeekClient,
+/// };
+/// # #[tokio::main]
+/// # async fn main() {
+/// let guardrail = Guardrail::new(DeepSeekClient::default().unwrap())
+///     .with_validator(SecretLeak::new())
+///     .with_validator(RegexRule::forbid(r"(?i)\bas an ai\b").unwrap())
+///     .with_validator(LengthLimit::default().with_max_chars(500))
+///     .with_retries(2);
+/// let request = RequestBody::new_messages(vec![Message::new_user_message(
+///     "How do I rotate my API key?".to_string(),
+/// )]);
+/// match guardrail.chat_completions(request).await {
+///     Ok(guarded) => println!("{:?}", guarded.response.choices[0].message.content),
+///     Err(e) => eprintln!("{}", e),
+/// }
+/// # }
+/// ```
+pub struct Guardrail {
+    provider: Arc<dyn ChatProvider>,
+    validators: Vec<Arc<dyn Validator>>,
+    retries: usize,
+    failing_finish_reasons: Vec<FinishReasons>,
+    feedback: Arc<Feedback>,
+}
+
+impl Guardrail {
+    /// Fails on content filtering and truncation, without retries
+    pub fn new(provider: impl ChatProvider + 'static) -> Self {
+        Guardrail {
+            provider: Arc::new(provider),
+            validators: Vec::new(),
+            retries: 0,
+            failing_finish_reasons: vec![FinishReasons::ContentFilter, FinishReasons::Length],
+            feedback: Arc::new(default_feedback),
+        }
+    }
+
+    /// Adds a validator; a reply passes when every validator accepts it
+    pub fn with_validator(mut self, validator: impl Validator + 'static) -> Self {
+        self.validators.push(Arc::new(validator));
+        self
+    }
+
+    /// Sets how many times a rejected reply is retried with feedback
+    pub fn with_retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Fails replies that end with `reason`
+    pub fn fail_on_finish_reason(mut self, reason: FinishReasons) -> Self {
+        if !self.failing_finish_reasons.contains(&reason) {
+            self.failing_finish_reasons.push(reason);
+        }
+        self
+    }
+
+    /// Accepts replies that end with `reason`, e.g. to keep truncated replies
+    pub fn allow_finish_reason(mut self, reason: FinishReasons) -> Self {
+        self.failing_finish_reasons.retain(|r| *r != reason);
+        self
+    }
+
+    /// Replaces the user message sent after a rejected reply
+    pub fn with_feedback(
+        mut self,
+        feedback: impl Fn(&[Violation]) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.feedback = Arc::new(feedback);
+        self
+    }
+
+    /// Runs every validator on `content`, e.g. on text collected from a stream
+    pub fn validate(&self, content: &str) -> Vec<Violation> {
+        self.validators
+            .iter()
+            .filter_map(|validator| {
+                validator.validate(content).err().map(|reason| Violation {
+                    validator: validator.name().to_string(),
+                    reason,
+                })
+            })
+            .collect()
+    }
+
+    /// Sends `request` and validates the first choice
+    ///
+    /// Replies that only call tools are not validated. After a rejection, the reply and
+    /// the feedback are appended to the conversation and the request is sent again.
+    pub async fn chat_completions(
+        &self,
+        mut request: RequestBody,
+    ) -> Result<GuardedResponse, GuardrailErrors> {
+        let mut usage = Usage::default();
+        let mut attempts = 0;
+        loop {
+            let response = self.provider.chat_completions(request.clone()).await?;
+            attempts += 1;
+            usage += &response.usage;
+            let Some(choice) = response.choices.first() else {
+                return Ok(GuardedResponse {
+                    response,
+                    attempts,
+                    usage,
+                });
+            };
+            if self.failing_finish_reasons.contains(&choice.finish_reason) {
+                return Err(GuardrailErrors::FinishReason {
+                    reason: choice.finish_reason.clone(),
+                    response: Box::new(response),
+                });
+            }
+            let violations = match choice.finish_reason {
+                FinishReasons::ToolCalls => Vec::new(),
+                _ => self.validate(choice.message.content.as_deref().unwrap_or_default()),
+            };
+            if violations.is_empty() {
+                return Ok(GuardedResponse {
+                    response,
+                    attempts,
+                    usage,
+                });
+            }
+            if attempts > self.retries {
+                return Err(GuardrailErrors::Rejected {
+                    attempts,
+                    violations,
+                    response: Box::new(response),
+                });
+            }
+            request.push_response(&response);
+            request.push_message(Message::new_user_message((self.feedback)(&violations)));
+        }
+    }
+}
+
+fn default_feedback(violations: &[Violation]) -> String {
+    let problems: Vec<String> = violations
+        .iter()
+        .map(|violation| format!("- {}", violation))
+        .collect();
+    format!(
+        "Your previous reply was rejected:\n{}\nReply to my previous message again, fixing \
+         these problems.",
+        problems.join("\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{validators::LengthLimit, *};
+    use crate::{
+        errors::request_errors::RequestErrors,
+        testing::{response_with, FakeProvider},
+    };
+
+    /// Fails every request after the scripted contents and finish reasons
+    fn script(replies: &[(&str, &str)]) -> Arc<FakeProvider> {
+        let script = replies.iter().fold(
+            FakeProvider::failing(),
+            |script, (content, finish_reason)| {
+                let message = json!({ "role": "assistant", "content": content });
+                script.then(Ok(response_with(message, finish_reason)))
+            },
+        );
+        Arc::new(script)
+    }
+
+    fn request() -> RequestBody {
+        RequestBody::new_messages(vec![Message::new_user_message("Hi".to_string())])
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_feedback() {
+        let script = script(&[
+            ("Hello there, how are you today?", "stop"),
+            ("Hello!", "stop"),
+        ]);
+        let guardrail = Guardrail::new(script.clone())
+            .with_validator(LengthLimit::default().with_max_chars(10))
+            .with_retries(1);
+        let guarded = guardrail.chat_completions(request()).await.unwrap();
+        assert_eq!(guarded.attempts, 2);
+        assert_eq!(guarded.usage.total_tokens, 4);
+        assert_eq!(
+            guarded.response.choices[0].message.content.as_deref(),
+            Some("Hello!")
+        );
+
+        let requests = script.requests();
+        let requests = requests.lock().unwrap();
+        let retry = requests[1].messages();
+        assert_eq!(retry.len(), 3);
+        assert_eq!(retry[1].content, "Hello there, how are you today?");
+        assert!(retry[2]
+            .content
+            .contains("- length: 31 characters is more than the maximum of 10"));
+    }
+
+    #[tokio::test]
+    async fn test_rejected() {
+        let script = script(&[("way too long", "stop"), ("still too long", "stop")]);
+        let guardrail = Guardrail::new(script.clone())
+            .with_validator(LengthLimit::default().with_max_chars(5))
+            .with_retries(1)
+            .with_feedback(|_| "Shorter.".to_string());
+        let err = guardrail.chat_completions(request()).await.unwrap_err();
+        let GuardrailErrors::Rejected {
+            attempts,
+            violations,
+            response,
+        } = err
+        else {
+            panic!("expected a rejection, got {:?}", err);
+        };
+        assert_eq!(attempts, 2);
+        assert_eq!(violations[0].validator, "length");
+        assert_eq!(
+            response.choices[0].message.content.as_deref(),
+            Some("still too long")
+        );
+        assert_eq!(
+            script.requests().lock().unwrap()[1].messages()[2].content,
+            "Shorter."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_finish_reasons() {
+        let script = script(&[("cut o", "length"), ("cut o", "length")]);
+        let guardrail = Guardrail::new(script.clone()).with_retries(3);
+        assert!(matches!(
+            guardrail.chat_completions(request()).await,
+            Err(GuardrailErrors::FinishReason {
+                reason: FinishReasons::Length,
+                ..
+            })
+        ));
+        assert_eq!(script.requests().lock().unwrap().len(), 1);
+
+        let guardrail = Guardrail::new(script).allow_finish_reason(FinishReasons::Length);
+        assert_eq!(
+            guardrail
+                .chat_completions(request())
+                .await
+                .unwrap()
+                .attempts,
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_error() {
+        let guardrail = Guardrail::new(FakeProvider::failing());
+        assert!(matches!(
+            guardrail.chat_completions(request()).await,
+            Err(GuardrailErrors::Request(RequestErrors::Unknown))
+        ));
+    }
+}
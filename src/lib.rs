@@ -4,8 +4,9 @@
 //!
 //! ## Usage
 //!
-//! ```no_run
-//! use deepseek_rs::{DeepSeekClient, client::chat_completions::request::{RequestBody, Message}};
+#![cfg_attr(feature = "client", doc = "```no_run")]
+#![cfg_attr(not(feature = "client"), doc = "```ignore")]
+//! use clia_deepseek_rs::{DeepSeekClient, client::chat_completions::request::{RequestBody, Message}};
 //!
 //! #[tokio::main]
 //! async fn main() {
@@ -16,10 +17,45 @@
 //!     client.chat_completions(request).await;
 //! }
 //! ```
+//!
+//! ## Features
+//!
+//! - `reqwest` (default): the async [`DeepSeekClient`] with its default transport
+//! - `client`: the async client without a transport; plug one in with
+//!   `DeepSeekClient::with_transport`. Without it the crate only provides [`types`],
+//!   [`conversation`], [`prompt`], [`rag`], [`schema`] and [`tokens`], with no HTTP stack.
+//! - `dotenv` (default): `DeepSeekClient::from_dotenv`
+//! - `chrono`: `created_at` timestamps on responses and chunks
+//! - `blocking`: a synchronous client running its own runtime
+//! - `tracing`: a span per request
+//! - `tower`: `tower::Service` for the client
+//! - `proxy`: the OpenAI-compatible `deepseek-proxy` server
 
+#[cfg(feature = "client")]
+pub mod agent;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "client")]
 pub mod client;
+pub mod conversation;
 pub mod errors;
+#[cfg(feature = "client")]
+pub mod eval;
+#[cfg(feature = "client")]
+pub mod guardrails;
+pub mod prompt;
+#[cfg(feature = "proxy")]
+pub mod proxy;
+pub mod rag;
+pub mod schema;
+#[cfg(feature = "client")]
+pub mod summarize;
+#[cfg(test)]
+pub(crate) mod testing;
+pub mod tokens;
+pub mod types;
 
 // Re-exports for convenience
-pub use client::chat_completions::request;
+#[cfg(feature = "client")]
 pub use client::client::DeepSeekClient;
+pub use types::request;
@@ -0,0 +1,5 @@
+//! Prompt construction helpers
+
+pub mod template;
+
+pub use template::PromptTemplate;
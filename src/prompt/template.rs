@@ -0,0 +1,340 @@
+//! Prompt templates with `{placeholders}` rendered into chat messages
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::Path,
+};
+
+use serde::Serialize;
+
+use crate::{
+    errors::template_errors::TemplateErrors,
+    types::request::{Message, Role},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Variable(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Section {
+    role: Role,
+    parts: Vec<Part>,
+}
+
+/// A prompt with `{name}` placeholders, rendered into request messages
+///
+/// Only `{` followed by an identifier and `}` is a placeholder; any other brace, such
+/// as in a JSON example, is kept as is. `{{` renders as a literal `{`, and so does the
+/// `}}` that closes it; a `}}` without a matching `{{` is kept as is.
+///
+/// A template whose first non-blank line is `[system]`, `[user]` or `[assistant]` is a
+/// chat template: each such line starts a new message with that role. Any other
+/// template renders to a single user message.
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::{prompt::PromptTemplate, request::Role};
+///
+/// let template = PromptTemplate::new(
+///     "[system]\nAnswer as JSON: {\"answer\": <value>}\n[user]\nWhat is {a} + {b}?",
+/// );
+/// let messages = template.render([("a", 2), ("b", 3)]).unwrap();
+/// assert!(matches!(messages[0].role, Role::System));
+/// assert_eq!(messages[0].content, "Answer as JSON: {\"answer\": <value>}");
+/// assert_eq!(messages[1].content, "What is 2 + 3?");
+/// assert!(template.render([("a", 2)]).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptTemplate {
+    sections: Vec<Section>,
+    variables: BTreeSet<String>,
+}
+
+impl PromptTemplate {
+    /// Parses a template
+    pub fn new(template: &str) -> Self {
+        let sections = split_sections(template)
+            .into_iter()
+            .map(|(role, body)| Section {
+                role,
+                parts: parse_parts(&body),
+            })
+            .collect::<Vec<_>>();
+        let variables = sections
+            .iter()
+            .flat_map(|s| &s.parts)
+            .filter_map(|part| match part {
+                Part::Variable(name) => Some(name.clone()),
+                Part::Text(_) => None,
+            })
+            .collect();
+        PromptTemplate {
+            sections,
+            variables,
+        }
+    }
+
+    /// Reads and parses a template file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, TemplateErrors> {
+        Ok(Self::new(&std::fs::read_to_string(path)?))
+    }
+
+    /// Returns the variables the template requires
+    pub fn variables(&self) -> &BTreeSet<String> {
+        &self.variables
+    }
+
+    /// Renders the template, requiring exactly the template's variables
+    pub fn render<K, V>(
+        &self,
+        variables: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<Vec<Message>, TemplateErrors>
+    where
+        K: Into<String>,
+        V: ToString,
+    {
+        let values: HashMap<String, String> = variables
+            .into_iter()
+            .map(|(k, v)| (k.into(), v.to_string()))
+            .collect();
+        let missing: Vec<String> = self
+            .variables
+            .iter()
+            .filter(|name| !values.contains_key(*name))
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            return Err(TemplateErrors::MissingVariables(missing));
+        }
+        let mut extra: Vec<String> = values
+            .keys()
+            .filter(|name| !self.variables.contains(*name))
+            .cloned()
+            .collect();
+        if !extra.is_empty() {
+            extra.sort();
+            return Err(TemplateErrors::UnexpectedVariables(extra));
+        }
+        Ok(self
+            .sections
+            .iter()
+            .map(|section| {
+                let content = section
+                    .parts
+                    .iter()
+                    .map(|part| match part {
+                        Part::Text(text) => text.as_str(),
+                        Part::Variable(name) => values[name].as_str(),
+                    })
+                    .collect();
+                Message::new(section.role.clone(), content, None)
+            })
+            .collect())
+    }
+
+    /// Renders the template with the fields of a serializable struct as variables
+    ///
+    /// String fields are inserted as is; other values are inserted as JSON.
+    pub fn render_from<T: Serialize>(&self, variables: &T) -> Result<Vec<Message>, TemplateErrors> {
+        let value = serde_json::to_value(variables)
+            .map_err(|e| TemplateErrors::InvalidVariables(e.to_string()))?;
+        let serde_json::Value::Object(object) = value else {
+            return Err(TemplateErrors::InvalidVariables(value.to_string()));
+        };
+        self.render(object.into_iter().map(|(k, v)| match v {
+            serde_json::Value::String(s) => (k, s),
+            v => (k, v.to_string()),
+        }))
+    }
+}
+
+fn section_role(line: &str) -> Option<Role> {
+    match line.trim() {
+        "[system]" => Some(Role::System),
+        "[user]" => Some(Role::User),
+        "[assistant]" => Some(Role::Assistant),
+        _ => None,
+    }
+}
+
+fn split_sections(template: &str) -> Vec<(Role, String)> {
+    let is_chat = template
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .and_then(section_role)
+        .is_some();
+    if !is_chat {
+        return vec![(Role::User, template.trim().to_string())];
+    }
+    let mut sections: Vec<(Role, Vec<&str>)> = Vec::new();
+    for line in template.lines() {
+        match (section_role(line), sections.last_mut()) {
+            (Some(role), _) => sections.push((role, Vec::new())),
+            (None, Some((_, lines))) => lines.push(line),
+            (None, None) => {}
+        }
+    }
+    sections
+        .into_iter()
+        .map(|(role, lines)| (role, lines.join("\n").trim().to_string()))
+        .collect()
+}
+
+fn parse_parts(body: &str) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut rest = body;
+    // Escaped `{{` still waiting for their `}}`
+    let mut open_escapes = 0usize;
+    while let Some(c) = rest.chars().next() {
+        let escape = if rest.starts_with("{{") {
+            open_escapes += 1;
+            true
+        } else if rest.starts_with("}}") && open_escapes > 0 {
+            open_escapes -= 1;
+            true
+        } else {
+            false
+        };
+        if escape {
+            text.push(c);
+            rest = &rest[2..];
+            continue;
+        }
+        if c == '{' {
+            if let Some(name) = placeholder(&rest[1..]) {
+                if !text.is_empty() {
+                    parts.push(Part::Text(std::mem::take(&mut text)));
+                }
+                parts.push(Part::Variable(name.to_string()));
+                rest = &rest[name.len() + 2..];
+                continue;
+            }
+        }
+        text.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    if !text.is_empty() {
+        parts.push(Part::Text(text));
+    }
+    parts
+}
+
+/// Returns the identifier if `s` starts with `identifier}`
+fn placeholder(s: &str) -> Option<&str> {
+    let end = s.find('}')?;
+    let name = &s[..end];
+    let mut chars = name.chars();
+    let first = chars.next()?;
+    let valid = (first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_message() {
+        let template = PromptTemplate::new("Translate {text} to {language}.");
+        assert_eq!(
+            template.variables().iter().collect::<Vec<_>>(),
+            ["language", "text"]
+        );
+        let messages = template
+            .render([("text", "hello"), ("language", "French")])
+            .unwrap();
+        assert_eq!(messages.len(), 1);
+        assert!(matches!(messages[0].role, Role::User));
+        assert_eq!(messages[0].content, "Translate hello to French.");
+    }
+
+    #[test]
+    fn test_braces() {
+        let template = PromptTemplate::new(r#"Reply {"a": [1, {x}]} {{name}} { name } {n-1}"#);
+        assert_eq!(template.variables().len(), 1);
+        let messages = template.render([("x", "{y}")]).unwrap();
+        assert_eq!(
+            messages[0].content,
+            r#"Reply {"a": [1, {y}]} {name} { name } {n-1}"#
+        );
+    }
+
+    #[test]
+    fn test_nested_json_braces() {
+        let template = PromptTemplate::new(r#"Like {"a": {"b": 1}} or {{"c": {{"d": {x}}}}}"#);
+        let messages = template.render([("x", 2)]).unwrap();
+        assert_eq!(
+            messages[0].content,
+            r#"Like {"a": {"b": 1}} or {"c": {"d": 2}}"#
+        );
+    }
+
+    #[test]
+    fn test_chat_sections() {
+        let template = PromptTemplate::new(
+            "\n[system]\nYou are {persona}.\n\n[user]\nHi\n[assistant]\nHello!\n[user]\n{question}\n",
+        );
+        let messages = template
+            .render([("persona", "terse"), ("question", "Why?")])
+            .unwrap();
+        let roles: Vec<_> = messages.iter().map(|m| m.role.clone()).collect();
+        assert_eq!(
+            roles,
+            [Role::System, Role::User, Role::Assistant, Role::User]
+        );
+        assert_eq!(messages[0].content, "You are terse.");
+        assert_eq!(messages[3].content, "Why?");
+    }
+
+    #[test]
+    fn test_missing_and_extra_variables() {
+        let template = PromptTemplate::new("{a} {b}");
+        assert!(matches!(
+            template.render([("a", "1")]),
+            Err(TemplateErrors::MissingVariables(v)) if v == ["b"]
+        ));
+        assert!(matches!(
+            template.render([("a", "1"), ("b", "2"), ("c", "3")]),
+            Err(TemplateErrors::UnexpectedVariables(v)) if v == ["c"]
+        ));
+    }
+
+    #[test]
+    fn test_render_from() {
+        #[derive(Serialize)]
+        struct Vars {
+            name: String,
+            count: u32,
+        }
+        let template = PromptTemplate::new("{name} has {count} items");
+        let messages = template
+            .render_from(&Vars {
+                name: "Ann".to_string(),
+                count: 3,
+            })
+            .unwrap();
+        assert_eq!(messages[0].content, "Ann has 3 items");
+        assert!(matches!(
+            template.render_from(&"nope"),
+            Err(TemplateErrors::InvalidVariables(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_file() {
+        let path = std::env::temp_dir().join(format!("deepseek_prompt_{}", std::process::id()));
+        std::fs::write(&path, "[system]\n{rules}\n").unwrap();
+        let template = PromptTemplate::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(template.render([("rules", "Be brief")]).unwrap().len(), 1);
+        assert!(matches!(
+            PromptTemplate::from_file(&path),
+            Err(TemplateErrors::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,471 @@
+//! OpenAI-compatible HTTP proxy
+//!
+//! Exposes `/v1/chat/completions` and `/v1/models` in the OpenAI wire format and
+//! forwards every call through a shared [`DeepSeekClient`], so anything configured on
+//! the client applies to all proxied callers.
+
+pub mod usage;
+
+use std::{collections::HashMap, convert::Infallible, sync::Arc};
+
+use axum::{
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Json, Router,
+};
+use futures::StreamExt;
+use serde_json::{json, Value};
+
+use crate::{
+    client::chat_completions::{
+        request::{Model, RequestBody, StreamOptions},
+        response::{ChatCompletionsChunk, Usage},
+        stream::StreamAccumulator,
+    },
+    errors::request_errors::{InterruptReason, RequestErrors},
+    tokens::estimate_tokens,
+    DeepSeekClient,
+};
+pub use usage::{MemoryUsageLog, StderrUsageLog, UsageLog, UsageRecord};
+
+/// Model aliases and caller tokens accepted by the proxy
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::{proxy::ProxyConfig, request::Model};
+///
+/// let config = ProxyConfig::default()
+///     .with_alias("gpt-4o".to_string(), Model::DeepseekChat)
+///     .with_caller("secret-token".to_string(), "build-bot".to_string());
+/// assert_eq!(config.resolve_model("gpt-4o"), Some(Model::DeepseekChat));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProxyConfig {
+    aliases: HashMap<String, Model>,
+    callers: HashMap<String, String>,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        let aliases = [Model::DeepseekChat, Model::DeepSeekReasoner]
+            .into_iter()
+            .map(|model| (model.to_string(), model))
+            .collect();
+        ProxyConfig {
+            aliases,
+            callers: HashMap::new(),
+        }
+    }
+}
+
+impl ProxyConfig {
+    /// Maps a model name sent by callers to a DeepSeek model
+    pub fn with_alias(mut self, alias: String, model: Model) -> Self {
+        self.aliases.insert(alias, model);
+        self
+    }
+
+    /// Accepts `Authorization: Bearer <token>` and logs usage under `name`.
+    /// Once any caller is registered, unauthenticated requests are rejected.
+    pub fn with_caller(mut self, token: String, name: String) -> Self {
+        self.callers.insert(token, name);
+        self
+    }
+
+    /// Resolves a model name or alias
+    pub fn resolve_model(&self, name: &str) -> Option<Model> {
+        self.aliases.get(name).cloned()
+    }
+
+    fn identify_caller(&self, headers: &HeaderMap, body: &Value) -> Option<String> {
+        if self.callers.is_empty() {
+            let user = body.get("user").and_then(Value::as_str);
+            return Some(user.unwrap_or("anonymous").to_string());
+        }
+        let token = headers
+            .get(AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")?;
+        self.callers.get(token).cloned()
+    }
+}
+
+struct ProxyState {
+    client: Arc<DeepSeekClient>,
+    config: ProxyConfig,
+    usage_log: Arc<dyn UsageLog>,
+}
+
+/// Builds the proxy routes
+pub fn router(
+    client: Arc<DeepSeekClient>,
+    config: ProxyConfig,
+    usage_log: Arc<dyn UsageLog>,
+) -> Router {
+    let state = Arc::new(ProxyState {
+        client,
+        config,
+        usage_log,
+    });
+    Router::new()
+        .route("/v1/chat/completions", post(chat_completions))
+        .route("/v1/models", get(models))
+        .with_state(state)
+}
+
+/// Serves the proxy on `listener` until the server fails
+pub async fn serve(
+    listener: tokio::net::TcpListener,
+    client: Arc<DeepSeekClient>,
+    config: ProxyConfig,
+    usage_log: Arc<dyn UsageLog>,
+) -> std::io::Result<()> {
+    axum::serve(listener, router(client, config, usage_log)).await
+}
+
+async fn models(State(state): State<Arc<ProxyState>>) -> Json<Value> {
+    let mut aliases: Vec<_> = state.config.aliases.iter().collect();
+    aliases.sort_by(|a, b| a.0.cmp(b.0));
+    let data: Vec<Value> = aliases
+        .into_iter()
+        .map(|(alias, model)| {
+            json!({
+                "id": alias,
+                "object": "model",
+                "owned_by": "deepseek",
+                "root": model.as_str(),
+            })
+        })
+        .collect();
+    Json(json!({ "object": "list", "data": data }))
+}
+
+async fn chat_completions(
+    State(state): State<Arc<ProxyState>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response {
+    let Some(caller) = state.config.identify_caller(&headers, &body) else {
+        return error_response(
+            StatusCode::UNAUTHORIZED,
+            "invalid_api_key",
+            "Invalid proxy API key",
+        );
+    };
+    let (request, stream) = match translate_request(&state.config, body) {
+        Ok(translated) => translated,
+        Err(response) => return *response,
+    };
+    if stream {
+        return stream_chat_completions(state, caller, request).await;
+    }
+    let model = request.model().clone();
+    match state.client.chat_completions(request).await {
+        Ok(response) => {
+            state.usage_log.record(&UsageRecord {
+                caller,
+                model,
+                usage: response.usage.clone(),
+                estimated: false,
+            });
+            Json(response).into_response()
+        }
+        Err(e) => request_error_response(&e),
+    }
+}
+
+async fn stream_chat_completions(
+    state: Arc<ProxyState>,
+    caller: String,
+    request: RequestBody,
+) -> Response {
+    let model = request.model().clone();
+    let prompt_tokens = estimate_prompt_tokens(&request);
+    let wants_usage = request.stream_options().is_some_and(|o| o.include_usage);
+    // Always ask for the trailing usage chunk so the call can be logged
+    let request = request.with_stream_options(StreamOptions {
+        include_usage: true,
+    });
+    let chunks = match state.client.chat_completions_stream(request).await {
+        Ok(chunks) => chunks,
+        Err(e) => return request_error_response(&e),
+    };
+    let mut usage = StreamUsage::new(state.usage_log.clone(), caller, model, prompt_tokens);
+    let events = chunks
+        .filter_map(move |chunk| {
+            let event = match chunk {
+                Ok(mut chunk) => {
+                    usage.push(&chunk);
+                    if !wants_usage {
+                        if chunk.choices.is_empty() {
+                            return futures::future::ready(None);
+                        }
+                        chunk.usage = None;
+                    }
+                    Event::default().json_data(&chunk).ok()
+                }
+                Err(e) => Event::default().json_data(error_body(&e)).ok(),
+            };
+            futures::future::ready(event.map(Ok::<_, Infallible>))
+        })
+        .chain(futures::stream::once(async {
+            Ok(Event::default().data("[DONE]"))
+        }));
+    Sse::new(events).into_response()
+}
+
+/// Estimates the prompt tokens of a request, one message at a time
+fn estimate_prompt_tokens(request: &RequestBody) -> usize {
+    request
+        .messages()
+        .iter()
+        .map(|message| estimate_tokens(&message.content))
+        .sum()
+}
+
+/// Logs a streamed call's usage when its stream is dropped, so callers that disconnect
+/// midway are still charged
+///
+/// Without a usage chunk, which only comes at the end of the stream, the tokens are
+/// estimated from the request's messages and the text streamed so far, and the record
+/// is marked as estimated.
+struct StreamUsage {
+    usage_log: Arc<dyn UsageLog>,
+    caller: String,
+    model: Model,
+    prompt_tokens: usize,
+    received: StreamAccumulator,
+}
+
+impl StreamUsage {
+    fn new(
+        usage_log: Arc<dyn UsageLog>,
+        caller: String,
+        model: Model,
+        prompt_tokens: usize,
+    ) -> Self {
+        StreamUsage {
+            usage_log,
+            caller,
+            model,
+            prompt_tokens,
+            received: StreamAccumulator::new(),
+        }
+    }
+
+    fn push(&mut self, chunk: &ChatCompletionsChunk) {
+        self.received.push(chunk);
+    }
+}
+
+impl Drop for StreamUsage {
+    fn drop(&mut self) {
+        let reported = self.received.usage().cloned();
+        let estimated = reported.is_none();
+        let usage = reported.unwrap_or_else(|| {
+            let prompt_tokens = self.prompt_tokens as i32;
+            let completion_tokens = (estimate_tokens(self.received.content())
+                + estimate_tokens(self.received.reasoning_content()))
+                as i32;
+            Usage {
+                prompt_tokens,
+                completion_tokens,
+                prompt_cache_miss_tokens: prompt_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+                ..Usage::default()
+            }
+        });
+        self.usage_log.record(&UsageRecord {
+            caller: std::mem::take(&mut self.caller),
+            model: self.model.clone(),
+            usage,
+            estimated,
+        });
+    }
+}
+
+/// Rewrites the OpenAI request into a [`RequestBody`], returning it with the stream flag
+fn translate_request(
+    config: &ProxyConfig,
+    mut body: Value,
+) -> Result<(RequestBody, bool), Box<Response>> {
+    let name = body
+        .get("model")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let Some(model) = config.resolve_model(&name) else {
+        return Err(Box::new(error_response(
+            StatusCode::NOT_FOUND,
+            "model_not_found",
+            &format!("The model `{name}` does not exist"),
+        )));
+    };
+    let stream = body.get("stream").and_then(Value::as_bool).unwrap_or(false);
+    body["model"] = json!(model);
+    serde_json::from_value::<RequestBody>(body)
+        .map(|request| (request, stream))
+        .map_err(|e| {
+            Box::new(error_response(
+                StatusCode::BAD_REQUEST,
+                "invalid_request_error",
+                &e.to_string(),
+            ))
+        })
+}
+
+fn status_for(error: &RequestErrors) -> StatusCode {
+    match error {
+        RequestErrors::BadRequest(_) => StatusCode::BAD_REQUEST,
+        RequestErrors::RateLimitExceeded(_) => StatusCode::TOO_MANY_REQUESTS,
+        RequestErrors::TimeoutError(_)
+        | RequestErrors::StreamInterrupted {
+            reason: InterruptReason::IdleTimeout,
+            ..
+        } => StatusCode::GATEWAY_TIMEOUT,
+        RequestErrors::StatusError(status, _) => *status,
+        RequestErrors::CircuitOpen(_) => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::BAD_GATEWAY,
+    }
+}
+
+fn error_body(error: &RequestErrors) -> Value {
+    json!({ "error": { "message": error.to_string(), "type": "upstream_error", "code": null } })
+}
+
+fn request_error_response(error: &RequestErrors) -> Response {
+    (status_for(error), Json(error_body(error))).into_response()
+}
+
+fn error_response(status: StatusCode, type_: &str, message: &str) -> Response {
+    let body = json!({ "error": { "message": message, "type": type_, "code": type_ } });
+    (status, Json(body)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::chat_completions::request::Message;
+
+    #[test]
+    fn test_resolve_model() {
+        let config = ProxyConfig::default().with_alias("o1".to_string(), Model::DeepSeekReasoner);
+        assert_eq!(
+            config.resolve_model("deepseek-chat"),
+            Some(Model::DeepseekChat)
+        );
+        assert_eq!(config.resolve_model("o1"), Some(Model::DeepSeekReasoner));
+        assert_eq!(config.resolve_model("gpt-4o"), None);
+    }
+
+    #[test]
+    fn test_identify_caller() {
+        let body = json!({ "user": "alice" });
+        let open = ProxyConfig::default();
+        assert_eq!(
+            open.identify_caller(&HeaderMap::new(), &body),
+            Some("alice".to_string())
+        );
+
+        let closed = ProxyConfig::default().with_caller("t0k3n".to_string(), "bot".to_string());
+        assert_eq!(closed.identify_caller(&HeaderMap::new(), &body), None);
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, "Bearer t0k3n".parse().unwrap());
+        assert_eq!(
+            closed.identify_caller(&headers, &body),
+            Some("bot".to_string())
+        );
+    }
+
+    #[test]
+    fn test_translate_request() {
+        let config = ProxyConfig::default().with_alias("gpt-4o".to_string(), Model::DeepseekChat);
+        let body = json!({
+            "model": "gpt-4o",
+            "messages": [{ "role": "user", "content": "Hi" }],
+            "stream": true,
+            "n": 1,
+        });
+        let (request, stream) = translate_request(&config, body).unwrap();
+        assert!(stream);
+        assert_eq!(request.model(), &Model::DeepseekChat);
+        assert_eq!(request.messages()[0].content, "Hi");
+
+        let unknown = json!({ "model": "gpt-4o-mini", "messages": [] });
+        let err = translate_request(&config, unknown).unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+    }
+
+    fn chunk(choices: Value, usage: Value) -> ChatCompletionsChunk {
+        serde_json::from_value(json!({
+            "id": "1",
+            "object": "chat.completion.chunk",
+            "created": 0,
+            "model": "deepseek-chat",
+            "choices": choices,
+            "usage": usage,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_stream_usage_logged_on_drop() {
+        let log = Arc::new(MemoryUsageLog::new());
+        let content = json!([{ "index": 0, "delta": { "content": "Hello there" } }]);
+
+        // The caller disconnected before the usage chunk arrived
+        let mut usage = StreamUsage::new(log.clone(), "bot".to_string(), Model::DeepseekChat, 1);
+        usage.push(&chunk(content.clone(), Value::Null));
+        drop(usage);
+        let estimated = &log.records()[0];
+        assert_eq!(estimated.caller, "bot");
+        assert_eq!(estimated.usage.prompt_tokens, 1);
+        assert_eq!(estimated.usage.completion_tokens, 4);
+        assert!(estimated.estimated);
+
+        let mut usage = StreamUsage::new(log.clone(), "bot".to_string(), Model::DeepseekChat, 1);
+        usage.push(&chunk(content, Value::Null));
+        usage.push(&chunk(
+            json!([]),
+            json!({ "completion_tokens": 2, "prompt_tokens": 5, "total_tokens": 7 }),
+        ));
+        drop(usage);
+        assert_eq!(log.records().len(), 2);
+        assert_eq!(log.records()[1].usage.total_tokens, 7);
+        assert!(!log.records()[1].estimated);
+    }
+
+    #[test]
+    fn test_estimate_prompt_tokens() {
+        let request = RequestBody::new_messages(vec![
+            Message::new_user_message("Hello".to_string()),
+            Message::new_user_message("world".to_string()),
+        ]);
+        assert_eq!(
+            estimate_prompt_tokens(&request),
+            2 * estimate_tokens("Hello")
+        );
+        assert_ne!(
+            estimate_prompt_tokens(&request),
+            estimate_tokens("Helloworld")
+        );
+    }
+
+    #[test]
+    fn test_status_for() {
+        assert_eq!(
+            status_for(&RequestErrors::RateLimitExceeded(String::new())),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(
+            status_for(&RequestErrors::Unauthorized(String::new())),
+            StatusCode::BAD_GATEWAY
+        );
+    }
+}
@@ -0,0 +1,64 @@
+//! Per-caller usage logging for the proxy
+
+use std::sync::Mutex;
+
+use crate::client::chat_completions::{request::Model, response::Usage};
+
+/// Receives the token usage of every completed proxied call
+pub trait UsageLog: Send + Sync {
+    fn record(&self, record: &UsageRecord);
+}
+
+/// Writes one line per call to stderr
+#[derive(Debug, Default)]
+pub struct StderrUsageLog;
+
+impl UsageLog for StderrUsageLog {
+    fn record(&self, record: &UsageRecord) {
+        let usage = &record.usage;
+        eprintln!(
+            "caller={} model={} prompt_tokens={} completion_tokens={} cache_hit_tokens={} total_tokens={} estimated={}",
+            record.caller,
+            record.model,
+            usage.prompt_tokens,
+            usage.completion_tokens,
+            usage.prompt_cache_hit_tokens,
+            usage.total_tokens,
+            record.estimated
+        );
+    }
+}
+
+/// The usage of one proxied call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageRecord {
+    pub caller: String,
+    pub model: Model,
+    pub usage: Usage,
+    /// Whether the tokens were estimated because a stream ended before the API
+    /// reported its usage
+    pub estimated: bool,
+}
+
+/// Keeps every record in memory, e.g. to expose totals from the embedding service
+#[derive(Debug, Default)]
+pub struct MemoryUsageLog {
+    records: Mutex<Vec<UsageRecord>>,
+}
+
+impl MemoryUsageLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a copy of all records logged so far
+    pub fn records(&self) -> Vec<UsageRecord> {
+        self.records.lock().unwrap().clone()
+    }
+}
+
+impl UsageLog for MemoryUsageLog {
+    fn record(&self, record: &UsageRecord) {
+        self.records.lock().unwrap().push(record.clone());
+    }
+}
@@ -0,0 +1,201 @@
+//! In-memory keyword retrieval with Okapi BM25
+
+use std::collections::HashMap;
+
+use super::{chunk::Chunk, Retriever, ScoredChunk};
+
+#[derive(Debug, Clone)]
+struct Document {
+    chunk: Chunk,
+    terms: HashMap<String, u32>,
+    length: usize,
+}
+
+/// A [`Retriever`] ranking chunks by BM25 over their words
+///
+/// Words are lowercased and split on anything that isn't alphanumeric; CJK characters
+/// count as one word each, since those scripts don't separate words with spaces.
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::rag::{bm25::Bm25Index, chunk::Chunk, Retriever};
+///
+/// let index = Bm25Index::default()
+///     .with_chunk(Chunk::new("a", "faq", "Refunds are issued within 14 days."))
+///     .with_chunk(Chunk::new("b", "faq", "Shipping takes 3 to 5 days."));
+/// let hits = index.retrieve("how long do refunds take", 1);
+/// assert_eq!(hits[0].chunk.id, "a");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Bm25Index {
+    k1: f64,
+    b: f64,
+    documents: Vec<Document>,
+    document_frequency: HashMap<String, u32>,
+    total_length: usize,
+}
+
+impl Default for Bm25Index {
+    /// Uses the common parameters `k1 = 1.2` and `b = 0.75`
+    fn default() -> Self {
+        Bm25Index::new(1.2, 0.75)
+    }
+}
+
+impl Bm25Index {
+    /// Creates an empty index; `k1` controls term frequency saturation and `b` length
+    /// normalization
+    pub fn new(k1: f64, b: f64) -> Self {
+        Bm25Index {
+            k1,
+            b,
+            documents: Vec::new(),
+            document_frequency: HashMap::new(),
+            total_length: 0,
+        }
+    }
+
+    pub fn add(&mut self, chunk: Chunk) {
+        let words = tokenize(&chunk.text);
+        let mut terms: HashMap<String, u32> = HashMap::new();
+        for word in &words {
+            *terms.entry(word.clone()).or_default() += 1;
+        }
+        for term in terms.keys() {
+            *self.document_frequency.entry(term.clone()).or_default() += 1;
+        }
+        self.total_length += words.len();
+        self.documents.push(Document {
+            chunk,
+            terms,
+            length: words.len(),
+        });
+    }
+
+    pub fn with_chunk(mut self, chunk: Chunk) -> Self {
+        self.add(chunk);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+}
+
+impl Extend<Chunk> for Bm25Index {
+    fn extend<T: IntoIterator<Item = Chunk>>(&mut self, chunks: T) {
+        for chunk in chunks {
+            self.add(chunk);
+        }
+    }
+}
+
+impl Retriever for Bm25Index {
+    fn retrieve(&self, query: &str, k: usize) -> Vec<ScoredChunk> {
+        if self.documents.is_empty() {
+            return Vec::new();
+        }
+        let mut query_terms = tokenize(query);
+        query_terms.sort();
+        query_terms.dedup();
+        let count = self.documents.len() as f64;
+        let average_length = (self.total_length as f64 / count).max(1.0);
+        let mut scored: Vec<ScoredChunk> = self
+            .documents
+            .iter()
+            .filter_map(|document| {
+                let score: f64 = query_terms
+                    .iter()
+                    .filter_map(|term| {
+                        let frequency = f64::from(*document.terms.get(term)?);
+                        let df = f64::from(self.document_frequency[term]);
+                        let idf = (1.0 + (count - df + 0.5) / (df + 0.5)).ln();
+                        let norm = 1.0 - self.b + self.b * document.length as f64 / average_length;
+                        Some(idf * frequency * (self.k1 + 1.0) / (frequency + self.k1 * norm))
+                    })
+                    .sum();
+                (score > 0.0).then(|| ScoredChunk {
+                    chunk: document.chunk.clone(),
+                    score,
+                })
+            })
+            .collect();
+        // Stable, so equal scores keep insertion order
+        scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+        scored.truncate(k);
+        scored
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{ac00}'..='\u{d7af}'
+        | '\u{f900}'..='\u{faff}')
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for c in text.chars() {
+        if is_cjk(c) {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            words.push(c.to_string());
+        } else if c.is_alphanumeric() {
+            word.extend(c.to_lowercase());
+        } else if !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> Bm25Index {
+        let mut index = Bm25Index::default();
+        index.extend([
+            Chunk::new("1", "a", "The cat sat on the mat."),
+            Chunk::new(
+                "2",
+                "a",
+                "Dogs chase cats. Cats climb trees to escape dogs.",
+            ),
+            Chunk::new("3", "b", "Stock markets fell sharply on Monday."),
+            Chunk::new("4", "c", "深度求索发布了新的模型"),
+        ]);
+        index
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(tokenize("Hello, WORLD-2!"), ["hello", "world", "2"]);
+        assert_eq!(tokenize("新模型v2"), ["新", "模", "型", "v2"]);
+    }
+
+    #[test]
+    fn test_ranking() {
+        let index = index();
+        let hits = index.retrieve("why do cats climb trees", 3);
+        assert_eq!(hits[0].chunk.id, "2");
+        assert!(hits.iter().all(|hit| hit.chunk.id != "3"));
+        assert!(hits.windows(2).all(|w| w[0].score >= w[1].score));
+
+        assert_eq!(index.retrieve("新模型", 1)[0].chunk.id, "4");
+        assert!(index.retrieve("quantum", 3).is_empty());
+        assert_eq!(index.retrieve("the cat", 1).len(), 1);
+    }
+}
@@ -0,0 +1,50 @@
+//! Splitting documents into retrievable chunks
+
+use serde::{Deserialize, Serialize};
+
+pub use crate::tokens::split_text;
+
+/// A passage of a source document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chunk {
+    /// `source#index`, unique as long as source names are
+    pub id: String,
+    /// Where the text came from, e.g. a file name or URL
+    pub source: String,
+    pub text: String,
+}
+
+impl Chunk {
+    pub fn new(id: impl Into<String>, source: impl Into<String>, text: impl Into<String>) -> Self {
+        Chunk {
+            id: id.into(),
+            source: source.into(),
+            text: text.into(),
+        }
+    }
+}
+
+/// Splits a document into chunks of about `max_tokens` with `overlap_tokens` shared
+/// between neighbours; see [`split_text`]
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::rag::chunk::chunk_document;
+///
+/// let chunks = chunk_document("guide.md", &"Rust is fast. ".repeat(40), 50, 10);
+/// assert_eq!(chunks[1].id, "guide.md#1");
+/// assert_eq!(chunks[1].source, "guide.md");
+/// ```
+pub fn chunk_document(
+    source: impl Into<String>,
+    text: &str,
+    max_tokens: usize,
+    overlap_tokens: usize,
+) -> Vec<Chunk> {
+    let source = source.into();
+    split_text(text, max_tokens, overlap_tokens)
+        .into_iter()
+        .enumerate()
+        .map(|(index, text)| Chunk::new(format!("{}#{}", source, index), source.clone(), text))
+        .collect()
+}
@@ -0,0 +1,305 @@
+//! Retrieval-augmented generation without embeddings
+//!
+//! A [`Retriever`] finds the chunks relevant to a question, such as the keyword-based
+//! [`Bm25Index`](bm25::Bm25Index). [`Rag`] puts the best of them, numbered, into the
+//! system message of a request and maps the `[n]` citations in the answer back to
+//! their chunks.
+
+pub mod bm25;
+pub mod chunk;
+
+use chunk::Chunk;
+
+#[cfg(feature = "client")]
+use crate::{
+    client::provider::ChatProvider, errors::request_errors::RequestErrors,
+    types::response::ChatCompletionsResponse,
+};
+use crate::{
+    tokens::estimate_tokens,
+    types::request::{Message, RequestBody, Role},
+};
+
+/// A chunk with its relevance to a query; higher is more relevant
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredChunk {
+    pub chunk: Chunk,
+    pub score: f64,
+}
+
+/// Finds the chunks most relevant to a query
+pub trait Retriever: Send + Sync {
+    /// Returns at most `k` chunks, most relevant first
+    fn retrieve(&self, query: &str, k: usize) -> Vec<ScoredChunk>;
+}
+
+impl<T: Retriever + ?Sized> Retriever for std::sync::Arc<T> {
+    fn retrieve(&self, query: &str, k: usize) -> Vec<ScoredChunk> {
+        (**self).retrieve(query, k)
+    }
+}
+
+/// A source the answer cites, by its number in the prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Citation {
+    pub number: usize,
+    pub chunk: Chunk,
+}
+
+/// A request with retrieved passages added, as returned by [`Rag::prepare`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RagRequest {
+    pub request: RequestBody,
+    /// The passages in the prompt; passage `[n]` is `sources[n - 1]`
+    pub sources: Vec<ScoredChunk>,
+}
+
+impl RagRequest {
+    /// Returns the sources cited as `[n]` (or `[n, m]`) in `answer`, in order of first
+    /// citation; numbers without a source are ignored
+    pub fn citations(&self, answer: &str) -> Vec<Citation> {
+        let mut numbers: Vec<usize> = Vec::new();
+        let mut rest = answer;
+        while let Some(open) = rest.find('[') {
+            rest = &rest[open + 1..];
+            let Some(close) = rest.find(']') else {
+                break;
+            };
+            // An unmatched `[` doesn't hide a citation that opens before the `]`
+            if let Some(inner) = rest[..close].rfind('[') {
+                rest = &rest[inner..];
+                continue;
+            }
+            let parsed: Result<Vec<usize>, _> = rest[..close]
+                .split(',')
+                .map(|n| n.trim().parse::<usize>())
+                .collect();
+            for number in parsed.into_iter().flatten() {
+                if (1..=self.sources.len()).contains(&number) && !numbers.contains(&number) {
+                    numbers.push(number);
+                }
+            }
+            rest = &rest[close + 1..];
+        }
+        numbers
+            .into_iter()
+            .map(|number| Citation {
+                number,
+                chunk: self.sources[number - 1].chunk.clone(),
+            })
+            .collect()
+    }
+}
+
+/// An answer from [`Rag::ask`] with the sources it was given and cites
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RagAnswer {
+    pub response: ChatCompletionsResponse,
+    pub sources: Vec<ScoredChunk>,
+    pub citations: Vec<Citation>,
+}
+
+/// Adds retrieved passages with citation numbers to requests
+///
+/// The query is the last user message. Passages are added in order of relevance as
+/// long as they fit the token budget, to the existing system message or a new one.
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::{
+///     rag::{bm25::Bm25Index, chunk::chunk_document, Rag},
+///     request::{Message, RequestBody},
+/// };
+///
+/// let mut index = Bm25Index::default();
+/// index.extend(chunk_document("policy.md", "Refunds are issued within 14 days.", 200, 0));
+/// let rag = Rag::new(index).with_top_k(3).with_token_budget(500);
+///
+/// let prepared = rag.prepare(RequestBody::new_messages(vec![Message::new_user_message(
+///     "When are refunds issued?".to_string(),
+/// )]));
+/// assert!(prepared.request.messages()[0].content.contains("[1] (policy.md) Refunds"));
+/// let cited = prepared.citations("Within 14 days [1].");
+/// assert_eq!(cited[0].chunk.id, "policy.md#0");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Rag<R> {
+    retriever: R,
+    top_k: usize,
+    token_budget: usize,
+    instructions: String,
+}
+
+impl<R: Retriever> Rag<R> {
+    /// Adds up to 4 passages within 2000 tokens
+    pub fn new(retriever: R) -> Self {
+        Rag {
+            retriever,
+            top_k: 4,
+            token_budget: 2000,
+            instructions: "Answer using the numbered sources below. Cite the sources you use \
+                           with their number in square brackets, like [1]. If the sources \
+                           don't contain the answer, say so."
+                .to_string(),
+        }
+    }
+
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k;
+        self
+    }
+
+    /// Limits the estimated tokens of the added passages
+    pub fn with_token_budget(mut self, tokens: usize) -> Self {
+        self.token_budget = tokens;
+        self
+    }
+
+    /// Replaces the text introducing the sources
+    pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = instructions.into();
+        self
+    }
+
+    pub fn retriever(&self) -> &R {
+        &self.retriever
+    }
+
+    /// Retrieves passages for the last user message and adds them to the system message
+    ///
+    /// Without a user message, or when nothing relevant is found, the request is
+    /// returned unchanged.
+    pub fn prepare(&self, request: RequestBody) -> RagRequest {
+        let query = request
+            .messages()
+            .iter()
+            .rev()
+            .find(|m| matches!(m.role, Role::User))
+            .map(|m| m.content.clone());
+        let Some(query) = query else {
+            return RagRequest {
+                request,
+                sources: Vec::new(),
+            };
+        };
+        let mut sources = Vec::new();
+        let mut passages = Vec::new();
+        let mut used = 0;
+        for hit in self.retriever.retrieve(&query, self.top_k) {
+            let passage = format!(
+                "[{}] ({}) {}",
+                sources.len() + 1,
+                hit.chunk.source,
+                hit.chunk.text
+            );
+            let cost = estimate_tokens(&passage);
+            if used + cost > self.token_budget {
+                continue;
+            }
+            used += cost;
+            passages.push(passage);
+            sources.push(hit);
+        }
+        if sources.is_empty() {
+            return RagRequest { request, sources };
+        }
+        let context = format!("{}\n\n{}", self.instructions, passages.join("\n\n"));
+        let mut messages = request.messages().to_vec();
+        match messages.first_mut() {
+            Some(system) if matches!(system.role, Role::System) => {
+                system.content = format!("{}\n\n{}", system.content, context);
+            }
+            _ => messages.insert(0, Message::new_system_message(context)),
+        }
+        RagRequest {
+            request: request.with_messages(messages),
+            sources,
+        }
+    }
+
+    /// Prepares `request`, sends it through `provider` and resolves the citations in
+    /// the first choice
+    #[cfg(feature = "client")]
+    pub async fn ask(
+        &self,
+        provider: &(impl ChatProvider + ?Sized),
+        request: RequestBody,
+    ) -> Result<RagAnswer, RequestErrors> {
+        let prepared = self.prepare(request);
+        let response = provider.chat_completions(prepared.request.clone()).await?;
+        let answer = response
+            .choices
+            .first()
+            .and_then(|choice| choice.message.content.as_deref())
+            .unwrap_or_default();
+        let citations = prepared.citations(answer);
+        Ok(RagAnswer {
+            response,
+            sources: prepared.sources,
+            citations,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bm25::Bm25Index, *};
+
+    fn rag() -> Rag<Bm25Index> {
+        let mut index = Bm25Index::default();
+        index.extend([
+            Chunk::new("a#0", "a", "Paris is the capital of France."),
+            Chunk::new("b#0", "b", "France borders Spain and Italy."),
+            Chunk::new("c#0", "c", "Bananas are yellow."),
+        ]);
+        Rag::new(index)
+    }
+
+    fn ask(question: &str) -> RequestBody {
+        RequestBody::new_messages(vec![
+            Message::new_system_message("Be brief.".to_string()),
+            Message::new_user_message(question.to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_prepare() {
+        let prepared = rag().prepare(ask("What is the capital of France?"));
+        assert_eq!(prepared.sources.len(), 2);
+        let messages = prepared.request.messages();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].content.starts_with("Be brief.\n\nAnswer using"));
+        assert!(messages[0].content.contains("[1] (a) Paris"));
+        assert!(messages[0].content.contains("[2] (b) France"));
+        assert!(!messages[0].content.contains("Bananas"));
+
+        let unchanged = rag().prepare(ask("quantum physics"));
+        assert!(unchanged.sources.is_empty());
+        assert_eq!(unchanged.request, ask("quantum physics"));
+    }
+
+    #[test]
+    fn test_token_budget() {
+        let prepared = rag()
+            .with_token_budget(12)
+            .prepare(RequestBody::new_messages(vec![Message::new_user_message(
+                "capital of France".to_string(),
+            )]));
+        assert_eq!(prepared.sources.len(), 1);
+        assert!(matches!(prepared.request.messages()[0].role, Role::System));
+    }
+
+    #[test]
+    fn test_citations() {
+        let prepared = rag().prepare(ask("What is the capital of France?"));
+        let citations = prepared.citations("Paris [1], next to Spain [2, 1]. See [7] [x] [");
+        let ids: Vec<_> = citations.iter().map(|c| c.chunk.id.as_str()).collect();
+        assert_eq!(ids, ["a#0", "b#0"]);
+        assert_eq!(citations[1].number, 2);
+
+        let citations = prepared.citations("Paris [a [2]");
+        assert_eq!(citations.len(), 1);
+        assert_eq!(citations[0].number, 2);
+    }
+}
@@ -0,0 +1,262 @@
+//! Validation of JSON values against a subset of JSON Schema
+//!
+//! Supports `type` (including a list of types), `enum`, `const`, `properties`,
+//! `required`, `additionalProperties: false`, `items`, `minItems`, `maxItems`,
+//! `minLength`, `maxLength`, `minimum`, `maximum`, `anyOf` and `allOf`. Other keywords
+//! are ignored, so schemas written for tools and structured output can be reused.
+
+use serde_json::Value;
+
+/// Checks `instance` against `schema`, returning every violation as
+/// `"<path>: <problem>"` with paths like `$.items[0].name`
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::schema::validate;
+/// use serde_json::json;
+///
+/// let schema = json!({
+///     "type": "object",
+///     "properties": { "age": { "type": "integer", "minimum": 0 } },
+///     "required": ["age"]
+/// });
+/// assert!(validate(&schema, &json!({ "age": 42 })).is_ok());
+/// assert_eq!(
+///     validate(&schema, &json!({ "age": -1 })).unwrap_err(),
+///     ["$.age: -1 is less than the minimum 0"]
+/// );
+/// ```
+pub fn validate(schema: &Value, instance: &Value) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+    check(schema, instance, "$", &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Strips a surrounding ```` ``` ```` or ```` ```json ```` fence, as models often wrap
+/// JSON replies in one
+pub fn strip_code_fence(output: &str) -> &str {
+    let trimmed = output.trim();
+    let Some(inner) = trimmed
+        .strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+    else {
+        return trimmed;
+    };
+    inner
+        .trim_start_matches(|c: char| c.is_ascii_alphanumeric())
+        .trim()
+}
+
+fn type_matches(name: &str, instance: &Value) -> bool {
+    match name {
+        "object" => instance.is_object(),
+        "array" => instance.is_array(),
+        "string" => instance.is_string(),
+        "number" => instance.is_number(),
+        "integer" => {
+            instance.is_i64()
+                || instance.is_u64()
+                || instance.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        "boolean" => instance.is_boolean(),
+        "null" => instance.is_null(),
+        _ => true,
+    }
+}
+
+fn check(schema: &Value, instance: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        // `true` and `{}` accept anything; `false` accepts nothing
+        if schema == &Value::Bool(false) {
+            errors.push(format!("{}: no value is allowed here", path));
+        }
+        return;
+    };
+    if let Some(expected) = schema.get("type") {
+        let names: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !names.is_empty() && !names.iter().any(|name| type_matches(name, instance)) {
+            errors.push(format!("{}: expected {}", path, names.join(" or ")));
+            return;
+        }
+    }
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(instance) {
+            errors.push(format!(
+                "{}: {} is not one of the allowed values",
+                path, instance
+            ));
+        }
+    }
+    if let Some(constant) = schema.get("const") {
+        if constant != instance {
+            errors.push(format!("{}: expected {}", path, constant));
+        }
+    }
+    if let Some(Value::Array(schemas)) = schema.get("allOf") {
+        for schema in schemas {
+            check(schema, instance, path, errors);
+        }
+    }
+    if let Some(Value::Array(schemas)) = schema.get("anyOf") {
+        let matches_one = schemas.iter().any(|schema| {
+            let mut nested = Vec::new();
+            check(schema, instance, path, &mut nested);
+            nested.is_empty()
+        });
+        if !matches_one {
+            errors.push(format!("{}: matches none of the allowed schemas", path));
+        }
+    }
+    match instance {
+        Value::Object(object) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            if let Some(Value::Array(required)) = schema.get("required") {
+                for name in required.iter().filter_map(Value::as_str) {
+                    if !object.contains_key(name) {
+                        errors.push(format!("{}: missing required property `{}`", path, name));
+                    }
+                }
+            }
+            for (name, value) in object {
+                let path = format!("{}.{}", path, name);
+                match properties.and_then(|properties| properties.get(name)) {
+                    Some(schema) => check(schema, value, &path, errors),
+                    None => {
+                        if let Some(additional) = schema.get("additionalProperties") {
+                            check(additional, value, &path, errors);
+                        }
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            check_bound(schema, "minItems", items.len(), path, errors, "items");
+            check_bound(schema, "maxItems", items.len(), path, errors, "items");
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    check(item_schema, item, &format!("{}[{}]", path, index), errors);
+                }
+            }
+        }
+        Value::String(string) => {
+            let length = string.chars().count();
+            check_bound(schema, "minLength", length, path, errors, "characters");
+            check_bound(schema, "maxLength", length, path, errors, "characters");
+        }
+        Value::Number(number) => {
+            let value = number.as_f64().unwrap_or_default();
+            if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64) {
+                if value < minimum {
+                    errors.push(format!(
+                        "{}: {} is less than the minimum {}",
+                        path, number, schema["minimum"]
+                    ));
+                }
+            }
+            if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64) {
+                if value > maximum {
+                    errors.push(format!(
+                        "{}: {} is more than the maximum {}",
+                        path, number, schema["maximum"]
+                    ));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_bound(
+    schema: &serde_json::Map<String, Value>,
+    keyword: &str,
+    actual: usize,
+    path: &str,
+    errors: &mut Vec<String>,
+    unit: &str,
+) {
+    let Some(bound) = schema.get(keyword).and_then(Value::as_u64) else {
+        return;
+    };
+    let bound = bound as usize;
+    let (violated, relation) = if keyword.starts_with("min") {
+        (actual < bound, "at least")
+    } else {
+        (actual > bound, "at most")
+    };
+    if violated {
+        errors.push(format!(
+            "{}: has {} {}, expected {} {}",
+            path, actual, unit, relation, bound
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_strip_code_fence() {
+        assert_eq!(strip_code_fence("```json\n{\"a\": 1}\n```"), "{\"a\": 1}");
+        assert_eq!(strip_code_fence("```\n[]\n```"), "[]");
+        assert_eq!(strip_code_fence("  {} "), "{}");
+    }
+
+    #[test]
+    fn test_nested() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "minLength": 1 },
+                "tags": { "type": "array", "items": { "enum": ["a", "b"] }, "maxItems": 2 },
+                "score": { "type": ["number", "null"] }
+            },
+            "required": ["name"],
+            "additionalProperties": false
+        });
+        assert!(validate(
+            &schema,
+            &json!({ "name": "x", "tags": ["a"], "score": null })
+        )
+        .is_ok());
+        let errors = validate(
+            &schema,
+            &json!({ "name": "", "tags": ["a", "c", "b"], "extra": 1, "score": "high" }),
+        )
+        .unwrap_err();
+        assert_eq!(
+            errors,
+            [
+                "$.extra: no value is allowed here",
+                "$.name: has 0 characters, expected at least 1",
+                "$.score: expected number or null",
+                "$.tags: has 3 items, expected at most 2",
+                "$.tags[1]: \"c\" is not one of the allowed values",
+            ]
+        );
+        assert_eq!(
+            validate(&schema, &json!([])).unwrap_err(),
+            ["$: expected object"]
+        );
+    }
+
+    #[test]
+    fn test_combinators_and_integers() {
+        let schema = json!({ "anyOf": [{ "type": "integer" }, { "const": "none" }] });
+        assert!(validate(&schema, &json!(3)).is_ok());
+        assert!(validate(&schema, &json!(3.0)).is_ok());
+        assert!(validate(&schema, &json!("none")).is_ok());
+        assert!(validate(&schema, &json!(3.5)).is_err());
+        assert!(validate(&json!({}), &json!({ "any": "thing" })).is_ok());
+    }
+}
@@ -0,0 +1,303 @@
+//! Map-reduce summaries of documents larger than the context window
+//!
+//! The text is split into overlapping chunks that are summarized concurrently (map),
+//! then the partial summaries are combined in groups that fit the input limit, round
+//! after round, until one summary is left (reduce).
+
+use std::sync::Arc;
+
+use futures::{stream, StreamExt, TryStreamExt};
+
+use crate::{
+    client::provider::ChatProvider,
+    errors::{request_errors::RequestErrors, template_errors::TemplateErrors},
+    prompt::PromptTemplate,
+    tokens::{estimate_tokens, split_text},
+    types::{request::RequestBody, response::Usage},
+};
+
+const MAP_PROMPT: &str = "[system]\nYou summarize documents accurately and concisely.\n\
+[user]\nSummarize this part of a longer document. Keep key facts, names and numbers.\n\n{text}";
+const REDUCE_PROMPT: &str = "[system]\nYou summarize documents accurately and concisely.\n\
+[user]\nCombine these partial summaries of one document into a single summary without \
+repetition.\n\n{text}";
+/// Separates partial summaries in a reduce call
+const SEPARATOR: &str = "\n\n---\n\n";
+
+/// The result of [`Summarizer::summarize`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    pub summary: String,
+    /// Usage of all calls together
+    pub usage: Usage,
+    /// Number of chat completion calls made
+    pub calls: usize,
+    /// Number of reduce rounds; 0 when the text fit in one chunk
+    pub rounds: usize,
+}
+
+/// Summarizes long texts with map and reduce calls through a [`ChatProvider`]
+///
+/// Map and reduce prompts are [`PromptTemplate`]s with a single `{text}` variable.
+///
+/// # Example
+/// ```no_run
+/// use clia_deepseek_rs::{summarize::Summarizer, DeepSeekClient};
+/// # #[tokio::main]
+/// # async fn main() {
+/// let text = std::fs::read_to_string("report.txt").unwrap();
+/// let summarizer = Summarizer::new(DeepSeekClient::default().unwrap())
+///     .with_chunk_tokens(4000, 200)
+///     .with_concurrency(8);
+/// let summary = summarizer.summarize(&text).await.unwrap();
+/// println!("{} ({} calls, {} tokens)", summary.summary, summary.calls, summary.usage.total_tokens);
+/// # }
+/// ```
+pub struct Summarizer {
+    provider: Arc<dyn ChatProvider>,
+    settings: RequestBody,
+    chunk_tokens: usize,
+    overlap_tokens: usize,
+    reduce_tokens: usize,
+    concurrency: usize,
+    map_prompt: PromptTemplate,
+    reduce_prompt: PromptTemplate,
+}
+
+impl Summarizer {
+    /// Uses chunks of 3000 tokens overlapping by 200, and 4 concurrent calls
+    pub fn new(provider: impl ChatProvider + 'static) -> Self {
+        Summarizer {
+            provider: Arc::new(provider),
+            settings: RequestBody::default(),
+            chunk_tokens: 3000,
+            overlap_tokens: 200,
+            reduce_tokens: 3000,
+            concurrency: 4,
+            map_prompt: PromptTemplate::new(MAP_PROMPT),
+            reduce_prompt: PromptTemplate::new(REDUCE_PROMPT),
+        }
+    }
+
+    /// Sets the model and sampling settings; messages in `settings` are ignored
+    pub fn with_settings(mut self, settings: RequestBody) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Sets the size of map chunks and how much neighbouring chunks share
+    pub fn with_chunk_tokens(mut self, chunk_tokens: usize, overlap_tokens: usize) -> Self {
+        self.chunk_tokens = chunk_tokens.max(1);
+        self.overlap_tokens = overlap_tokens;
+        self
+    }
+
+    /// Sets how many tokens of partial summaries a reduce call combines
+    pub fn with_reduce_tokens(mut self, tokens: usize) -> Self {
+        self.reduce_tokens = tokens.max(1);
+        self
+    }
+
+    /// Sets how many calls run at once
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Replaces the prompt that summarizes each chunk
+    pub fn with_map_prompt(mut self, prompt: PromptTemplate) -> Result<Self, TemplateErrors> {
+        check_prompt(&prompt)?;
+        self.map_prompt = prompt;
+        Ok(self)
+    }
+
+    /// Replaces the prompt that combines partial summaries, separated by `---` lines
+    pub fn with_reduce_prompt(mut self, prompt: PromptTemplate) -> Result<Self, TemplateErrors> {
+        check_prompt(&prompt)?;
+        self.reduce_prompt = prompt;
+        Ok(self)
+    }
+
+    /// Summarizes `text`
+    ///
+    /// Fails with the first request error; calls still running are dropped.
+    pub async fn summarize(&self, text: &str) -> Result<Summary, RequestErrors> {
+        let mut summary = Summary {
+            summary: String::new(),
+            usage: Usage::default(),
+            calls: 0,
+            rounds: 0,
+        };
+        let chunks = split_text(text, self.chunk_tokens, self.overlap_tokens);
+        let mut partials = self.run(&self.map_prompt, chunks, &mut summary).await?;
+        while partials.len() > 1 {
+            let groups = self.group(partials);
+            partials = self.run(&self.reduce_prompt, groups, &mut summary).await?;
+            summary.rounds += 1;
+        }
+        summary.summary = partials.pop().unwrap_or_default();
+        Ok(summary)
+    }
+
+    /// Joins partial summaries into groups that fit the reduce limit, pairing them up
+    /// when none fit together so every round shrinks the list
+    fn group(&self, partials: Vec<String>) -> Vec<String> {
+        let count = partials.len();
+        let mut groups: Vec<Vec<String>> = Vec::new();
+        let mut used = 0;
+        for partial in partials {
+            let cost = estimate_tokens(&partial);
+            match groups.last_mut() {
+                Some(group) if used + cost <= self.reduce_tokens => {
+                    group.push(partial);
+                    used += cost;
+                }
+                _ => {
+                    groups.push(vec![partial]);
+                    used = cost;
+                }
+            }
+        }
+        if groups.len() == count {
+            groups = groups
+                .chunks(2)
+                .map(|pair| pair.iter().flatten().cloned().collect())
+                .collect();
+        }
+        groups.iter().map(|group| group.join(SEPARATOR)).collect()
+    }
+
+    /// Sends one call per input, in order, with at most `concurrency` at once
+    async fn run(
+        &self,
+        prompt: &PromptTemplate,
+        inputs: Vec<String>,
+        summary: &mut Summary,
+    ) -> Result<Vec<String>, RequestErrors> {
+        let responses: Vec<_> = stream::iter(inputs)
+            .map(|text| {
+                let messages = prompt
+                    .render([("text", text)])
+                    .expect("prompts are checked to take only {text}");
+                self.provider
+                    .chat_completions(self.settings.clone().with_messages(messages))
+            })
+            .buffered(self.concurrency)
+            .try_collect()
+            .await?;
+        Ok(responses
+            .into_iter()
+            .map(|response| {
+                summary.usage += &response.usage;
+                summary.calls += 1;
+                response
+                    .choices
+                    .into_iter()
+                    .next()
+                    .and_then(|choice| choice.message.content)
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string()
+            })
+            .collect())
+    }
+}
+
+fn check_prompt(prompt: &PromptTemplate) -> Result<(), TemplateErrors> {
+    if !prompt.variables().contains("text") {
+        return Err(TemplateErrors::MissingVariables(vec!["text".to_string()]));
+    }
+    let extra: Vec<String> = prompt
+        .variables()
+        .iter()
+        .filter(|name| *name != "text")
+        .cloned()
+        .collect();
+    if !extra.is_empty() {
+        return Err(TemplateErrors::UnexpectedVariables(extra));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::atomic::Ordering, time::Duration};
+
+    use super::*;
+    use crate::testing::{response, FakeProvider};
+
+    /// Summarizes by keeping the first three words like `w42` of the prompt
+    fn keywords() -> FakeProvider {
+        FakeProvider::new(|request| {
+            let prompt = &request.messages().last().unwrap().content;
+            let words: Vec<&str> = prompt
+                .split_whitespace()
+                .filter(|word| {
+                    word.strip_prefix('w')
+                        .is_some_and(|n| n.len() == 2 && n.chars().all(|c| c.is_ascii_digit()))
+                })
+                .take(3)
+                .collect();
+            Ok(response(&words.join(" ")))
+        })
+        .with_delay(Duration::from_millis(5))
+    }
+
+    #[tokio::test]
+    async fn test_map_reduce() {
+        let provider = keywords();
+        let requests = provider.requests();
+        let max_running = provider.max_running();
+        let text = (0..100)
+            .map(|i| format!("w{:02}", i))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let summarizer = Summarizer::new(provider)
+            .with_chunk_tokens(30, 0)
+            .with_reduce_tokens(8)
+            .with_concurrency(3);
+        let summary = summarizer.summarize(&text).await.unwrap();
+
+        // 100 words of 4 characters at 0.3 tokens each make 4 chunks of 25 words; each
+        // partial keeps 3 words (~4 tokens), so pairs fit a reduce call
+        assert_eq!(summary.rounds, 2);
+        assert_eq!(summary.calls, 4 + 2 + 1);
+        assert_eq!(summary.usage.total_tokens, 14);
+        assert_eq!(summary.summary, "w00 w01 w02");
+        assert_eq!(max_running.load(Ordering::SeqCst), 3);
+        let prompts: Vec<String> = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|request| request.messages().last().unwrap().content.clone())
+            .collect();
+        assert!(prompts[0].starts_with("Summarize this part"));
+        assert!(prompts[4].starts_with("Combine these") && prompts[4].contains("\n---\n"));
+    }
+
+    #[tokio::test]
+    async fn test_short_text_and_custom_prompts() {
+        let summarizer = Summarizer::new(keywords())
+            .with_map_prompt(PromptTemplate::new("Gist: {text}"))
+            .unwrap();
+        let summary = summarizer.summarize("one word here").await.unwrap();
+        assert_eq!((summary.calls, summary.rounds), (1, 0));
+
+        assert!(matches!(
+            Summarizer::new(keywords()).with_reduce_prompt(PromptTemplate::new("{a}")),
+            Err(TemplateErrors::MissingVariables(_))
+        ));
+        assert!(matches!(
+            Summarizer::new(keywords())
+                .with_map_prompt(PromptTemplate::new("{text} {lang}")),
+            Err(TemplateErrors::UnexpectedVariables(v)) if v == ["lang"]
+        ));
+    }
+
+    #[test]
+    fn test_group_always_shrinks() {
+        let summarizer = Summarizer::new(keywords()).with_reduce_tokens(1);
+        let groups = summarizer.group(vec!["aaaa".to_string(); 3]);
+        assert_eq!(groups.len(), 2);
+    }
+}
@@ -0,0 +1,166 @@
+//! Fixtures shared by the unit tests
+
+use serde_json::{json, Value};
+
+use crate::types::response::ChatCompletionsResponse;
+#[cfg(feature = "client")]
+pub(crate) use fake::FakeProvider;
+
+/// A complete non-streaming response replying `content`, as JSON
+pub(crate) fn response_json(content: &str) -> Value {
+    json!({
+        "id": "1",
+        "object": "chat.completion",
+        "created": 0,
+        "model": "deepseek-chat",
+        "choices": [{
+            "index": 0,
+            "finish_reason": "stop",
+            "message": { "role": "assistant", "content": content }
+        }],
+        "usage": {
+            "completion_tokens": 1, "prompt_tokens": 1, "prompt_cache_hit_tokens": 0,
+            "prompt_cache_miss_tokens": 1, "total_tokens": 2
+        }
+    })
+}
+
+/// A complete non-streaming response replying `content`
+#[cfg(feature = "client")]
+pub(crate) fn response(content: &str) -> ChatCompletionsResponse {
+    serde_json::from_value(response_json(content)).unwrap()
+}
+
+/// A response whose first choice is `message` and ends with `finish_reason`
+pub(crate) fn response_with(message: Value, finish_reason: &str) -> ChatCompletionsResponse {
+    let mut body = response_json("");
+    body["choices"][0]["message"] = message;
+    body["choices"][0]["finish_reason"] = json!(finish_reason);
+    serde_json::from_value(body).unwrap()
+}
+
+#[cfg(feature = "client")]
+mod fake {
+    use std::{
+        collections::VecDeque,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use futures::future::BoxFuture;
+
+    use super::response;
+    use crate::{
+        client::{chat_completions::stream::ChatCompletionsStream, provider::ChatProvider},
+        errors::request_errors::RequestErrors,
+        types::{request::RequestBody, response::ChatCompletionsResponse},
+    };
+
+    type Respond =
+        dyn Fn(&RequestBody) -> Result<ChatCompletionsResponse, RequestErrors> + Send + Sync;
+
+    /// A [`ChatProvider`] that answers with queued outcomes, then from a closure
+    ///
+    /// It keeps every request it receives and the highest number of calls it ran at
+    /// once. Streaming always fails.
+    pub(crate) struct FakeProvider {
+        name: &'static str,
+        queue: Mutex<VecDeque<Result<ChatCompletionsResponse, RequestErrors>>>,
+        respond: Box<Respond>,
+        delay: Duration,
+        requests: Arc<Mutex<Vec<RequestBody>>>,
+        running: AtomicUsize,
+        max_running: Arc<AtomicUsize>,
+    }
+
+    impl FakeProvider {
+        /// Answers every request that finds the queue empty with `respond`
+        pub(crate) fn new(
+            respond: impl Fn(&RequestBody) -> Result<ChatCompletionsResponse, RequestErrors>
+                + Send
+                + Sync
+                + 'static,
+        ) -> Self {
+            FakeProvider {
+                name: "fake",
+                queue: Mutex::default(),
+                respond: Box::new(respond),
+                delay: Duration::ZERO,
+                requests: Arc::default(),
+                running: AtomicUsize::new(0),
+                max_running: Arc::default(),
+            }
+        }
+
+        /// Answers every request that finds the queue empty with `content`
+        pub(crate) fn replying(content: &'static str) -> Self {
+            Self::new(move |_| Ok(response(content)))
+        }
+
+        /// Fails every request that finds the queue empty
+        pub(crate) fn failing() -> Self {
+            Self::new(|_| Err(RequestErrors::Unknown))
+        }
+
+        pub(crate) fn with_name(mut self, name: &'static str) -> Self {
+            self.name = name;
+            self
+        }
+
+        /// Delays every answer
+        pub(crate) fn with_delay(mut self, delay: Duration) -> Self {
+            self.delay = delay;
+            self
+        }
+
+        /// Queues an outcome for the next request
+        pub(crate) fn then(self, outcome: Result<ChatCompletionsResponse, RequestErrors>) -> Self {
+            self.queue.lock().unwrap().push_back(outcome);
+            self
+        }
+
+        /// Returns a handle to the requests received so far
+        pub(crate) fn requests(&self) -> Arc<Mutex<Vec<RequestBody>>> {
+            self.requests.clone()
+        }
+
+        /// Returns a handle to the highest number of calls run at once
+        pub(crate) fn max_running(&self) -> Arc<AtomicUsize> {
+            self.max_running.clone()
+        }
+    }
+
+    impl ChatProvider for FakeProvider {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn chat_completions(
+            &self,
+            request: RequestBody,
+        ) -> BoxFuture<'_, Result<ChatCompletionsResponse, RequestErrors>> {
+            let queued = self.queue.lock().unwrap().pop_front();
+            let outcome = queued.unwrap_or_else(|| (self.respond)(&request));
+            self.requests.lock().unwrap().push(request);
+            Box::pin(async move {
+                let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+                self.max_running.fetch_max(running, Ordering::SeqCst);
+                if !self.delay.is_zero() {
+                    tokio::time::sleep(self.delay).await;
+                }
+                self.running.fetch_sub(1, Ordering::SeqCst);
+                outcome
+            })
+        }
+
+        fn chat_completions_stream(
+            &self,
+            _request: RequestBody,
+        ) -> BoxFuture<'_, Result<ChatCompletionsStream, RequestErrors>> {
+            Box::pin(async { Err(RequestErrors::Unknown) })
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Rough token counts for budgeting without a tokenizer
+//!
+//! DeepSeek documents about 0.3 tokens per English character and 0.6 per Chinese
+//! character. That is close enough to keep prompts within a budget, but not for billing;
+//! use the `Usage` of responses for that.
+
+/// Estimates the number of tokens `text` uses
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::tokens::estimate_tokens;
+///
+/// assert_eq!(estimate_tokens("Hello, world"), 4);
+/// assert_eq!(estimate_tokens("你好"), 2);
+/// assert_eq!(estimate_tokens(""), 0);
+/// ```
+pub fn estimate_tokens(text: &str) -> usize {
+    cost_tenths(text).div_ceil(10)
+}
+
+/// Estimated tokens in tenths, so pieces of a text can be added up without rounding
+fn cost_tenths(text: &str) -> usize {
+    text.chars().map(char_tenths).sum()
+}
+
+fn char_tenths(c: char) -> usize {
+    if c.is_ascii() {
+        3
+    } else {
+        6
+    }
+}
+
+/// Splits `text` into chunks of at most about `max_tokens`, where consecutive chunks
+/// share about `overlap_tokens` of text
+///
+/// Chunks end at whitespace, and at the end of a sentence or line when one falls in
+/// the second half of the chunk. Text without such breaks, like a long word, is split
+/// anywhere. The overlap is capped at half of `max_tokens`.
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::tokens::{estimate_tokens, split_text};
+///
+/// let text = "One sentence here. ".repeat(50);
+/// let chunks = split_text(&text, 40, 10);
+/// assert!(chunks.len() > 1);
+/// assert!(chunks.iter().all(|chunk| estimate_tokens(chunk) <= 40));
+/// assert!(chunks[0].ends_with('.'));
+/// ```
+pub fn split_text(text: &str, max_tokens: usize, overlap_tokens: usize) -> Vec<String> {
+    let max = max_tokens.max(1) * 10;
+    let overlap = overlap_tokens.min(max_tokens / 2) * 10;
+    let pieces = pieces(text, max);
+    let costs: Vec<usize> = pieces.iter().map(|piece| cost_tenths(piece)).collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < pieces.len() {
+        let mut end = start;
+        let mut used = 0;
+        while end < pieces.len() && (end == start || used + costs[end] <= max) {
+            used += costs[end];
+            end += 1;
+        }
+        if end < pieces.len() {
+            // Back off to a sentence end, as long as that keeps at least half the chunk
+            let mut kept = used;
+            for boundary in (start + 1..end).rev() {
+                kept -= costs[boundary];
+                if kept * 2 < used {
+                    break;
+                }
+                if ends_sentence(pieces[boundary - 1]) {
+                    end = boundary;
+                    break;
+                }
+            }
+        }
+        let chunk = pieces[start..end].concat();
+        if !chunk.trim().is_empty() {
+            chunks.push(chunk.trim().to_string());
+        }
+        if end == pieces.len() {
+            break;
+        }
+        let mut next = end;
+        let mut shared = 0;
+        while next > start + 1 && shared + costs[next - 1] <= overlap {
+            next -= 1;
+            shared += costs[next];
+        }
+        start = next;
+    }
+    chunks
+}
+
+/// Splits `text` after whitespace and CJK sentence punctuation, breaking pieces that
+/// alone exceed `max` tenths of a token
+fn pieces(text: &str, max: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut cost = 0;
+    for (i, c) in text.char_indices() {
+        let next = i + c.len_utf8();
+        if cost + char_tenths(c) > max && i > start {
+            pieces.push(&text[start..i]);
+            start = i;
+            cost = 0;
+        }
+        cost += char_tenths(c);
+        if c.is_whitespace() || matches!(c, '。' | '！' | '？' | '；') {
+            pieces.push(&text[start..next]);
+            start = next;
+            cost = 0;
+        }
+    }
+    if start < text.len() {
+        pieces.push(&text[start..]);
+    }
+    pieces
+}
+
+fn ends_sentence(piece: &str) -> bool {
+    piece.contains('\n')
+        || piece
+            .trim_end()
+            .ends_with(['.', '!', '?', '。', '！', '？'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_sizes_and_overlap() {
+        let text = (0..200)
+            .map(|i| format!("w{:03}", i))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let chunks = split_text(&text, 30, 6);
+        assert!(chunks.iter().all(|chunk| estimate_tokens(chunk) <= 30));
+        // Consecutive chunks share their boundary words
+        for pair in chunks.windows(2) {
+            let last_word = pair[0].split(' ').next_back().unwrap();
+            assert!(pair[1].starts_with(last_word) || pair[1].contains(last_word));
+        }
+        let words: std::collections::BTreeSet<&str> =
+            chunks.iter().flat_map(|chunk| chunk.split(' ')).collect();
+        assert_eq!(words.len(), 200);
+    }
+
+    #[test]
+    fn test_split_without_breaks() {
+        let text = "字".repeat(100);
+        let chunks = split_text(&text, 12, 0);
+        assert_eq!(chunks.len(), 5);
+        assert_eq!(chunks.concat(), text);
+        assert!(split_text("  ", 10, 0).is_empty());
+    }
+
+    #[test]
+    fn test_split_prefers_sentences() {
+        let text = "A short one. Then a much longer second sentence follows here";
+        let chunks = split_text(text, 8, 0);
+        assert_eq!(chunks[0], "A short one.");
+    }
+}
@@ -0,0 +1,7 @@
+//! Request and response types of the chat completions API
+//!
+//! These only depend on `serde`, so they can be used without the HTTP client, e.g.
+//! to build requests on one machine and send them from another.
+
+pub mod request;
+pub mod response;
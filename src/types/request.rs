@@ -6,11 +6,14 @@ use std::fmt;
 
 use serde::{Deserialize, Serialize};
 
+use super::response::{ChatCompletionsResponse, Message as ResponseMessage, ToolsCall};
+use crate::errors::message_errors::MessageErrors;
+
 /// A chat completion request body
 ///
 /// # Example
 /// ```
-/// use deepseek_rs::client::chat_completions::request::{RequestBody, Message};
+/// use clia_deepseek_rs::types::request::{RequestBody, Message};
 ///
 /// let request = RequestBody::new_messages(
 ///     vec![Message::new_user_message("Hello".to_string())]
@@ -31,6 +34,12 @@ pub struct RequestBody {
     top_p: Option<TopP>,
     logprobs: Option<bool>,
     top_logprobs: Option<TopLogProbs>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<Tool>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<ToolChoice>,
+    #[serde(skip)]
+    cache_bypass: bool,
 }
 
 impl RequestBody {
@@ -38,7 +47,7 @@ impl RequestBody {
     ///
     /// # Examples
     /// ```
-    /// use deepseek_rs::client::chat_completions::request::{RequestBody, Message, Model};
+    /// use clia_deepseek_rs::types::request::{RequestBody, Message, Model};
     ///
     /// let request = RequestBody::new(
     ///     vec![Message::new_user_message("Hello".to_string())],
@@ -57,7 +66,7 @@ impl RequestBody {
     ///
     /// # Examples
     /// ```
-    /// use deepseek_rs::client::chat_completions::request::{RequestBody, Message};
+    /// use clia_deepseek_rs::types::request::{RequestBody, Message};
     ///
     /// let request = RequestBody::new_messages(
     ///     vec![Message::new_user_message("Hello".to_string())]
@@ -147,6 +156,80 @@ impl RequestBody {
         self.top_logprobs = Some(top_logprobs);
         self
     }
+
+    /// Sets the functions the model may call; an empty list removes them
+    pub fn with_tools(mut self, tools: Vec<Tool>) -> Self {
+        self.tools = (!tools.is_empty()).then_some(tools);
+        self
+    }
+
+    /// Sets whether and which tool the model must call
+    pub fn with_tool_choice(mut self, choice: ToolChoice) -> Self {
+        self.tool_choice = Some(choice);
+        self
+    }
+
+    /// Appends the first choice of `response` as an assistant message
+    ///
+    /// The reply's `reasoning_content` is dropped, since the API doesn't accept it in
+    /// input messages.
+    ///
+    /// # Examples
+    /// ```
+    /// use clia_deepseek_rs::types::{
+    ///     request::{Message, RequestBody},
+    ///     response::ChatCompletionsResponse,
+    /// };
+    ///
+    /// fn follow_up(mut request: RequestBody, response: &ChatCompletionsResponse) -> RequestBody {
+    ///     request.push_response(response);
+    ///     request.push_message(Message::new_user_message("What is its population?".to_string()));
+    ///     request
+    /// }
+    /// ```
+    pub fn push_response(&mut self, response: &ChatCompletionsResponse) {
+        if let Some(choice) = response.choices.first() {
+            let mut message = Message::from(choice.message.clone());
+            message.reasoning_content = None;
+            self.messages.push(message);
+        }
+    }
+
+    /// Appends a message
+    pub fn push_message(&mut self, message: Message) {
+        self.messages.push(message);
+    }
+
+    /// Skips any response cache configured on the client for this request
+    pub fn with_cache_bypass(mut self, bypass: bool) -> Self {
+        self.cache_bypass = bypass;
+        self
+    }
+
+    /// Returns the messages of this request
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    /// Returns the model of this request
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// Returns the streaming options of this request
+    pub fn stream_options(&self) -> Option<&StreamOptions> {
+        self.stream_options.as_ref()
+    }
+
+    /// Returns the functions the model may call
+    pub fn tools(&self) -> &[Tool] {
+        self.tools.as_deref().unwrap_or_default()
+    }
+
+    /// Returns whether this request skips the response cache
+    pub fn cache_bypass(&self) -> bool {
+        self.cache_bypass
+    }
 }
 
 impl Default for RequestBody {
@@ -165,6 +248,9 @@ impl Default for RequestBody {
             top_p: None,
             logprobs: None,
             top_logprobs: None,
+            tools: None,
+            tool_choice: None,
+            cache_bypass: false,
         }
     }
 }
@@ -178,11 +264,27 @@ pub enum Model {
     DeepSeekReasoner,
 }
 
+impl Model {
+    /// Returns the model name as sent to the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Model::DeepseekChat => "deepseek-chat",
+            Model::DeepSeekReasoner => "deepseek-reasoner",
+        }
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
 /// Frequency penalty value between -2 and 2
 ///
 /// # Examples
 /// ```
-/// use deepseek_rs::client::chat_completions::request::FrequencyPenalty;
+/// use clia_deepseek_rs::types::request::FrequencyPenalty;
 ///
 /// let penalty = FrequencyPenalty::new(1);
 /// assert_eq!(penalty.to_string(), "1");
@@ -191,7 +293,7 @@ pub enum Model {
 /// let max_penalty = FrequencyPenalty::new(3);
 /// assert_eq!(max_penalty.to_string(), "2");
 /// ```
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
 pub struct FrequencyPenalty(i8);
 
 impl FrequencyPenalty {
@@ -206,11 +308,6 @@ impl FrequencyPenalty {
     }
 }
 
-impl Default for FrequencyPenalty {
-    fn default() -> Self {
-        FrequencyPenalty(0)
-    }
-}
 impl fmt::Display for FrequencyPenalty {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.0)
@@ -244,7 +341,7 @@ impl fmt::Display for MaxTokens {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
 
 pub struct PresencePenalty(i8);
 
@@ -260,11 +357,6 @@ impl PresencePenalty {
     }
 }
 
-impl Default for PresencePenalty {
-    fn default() -> Self {
-        PresencePenalty(0)
-    }
-}
 impl fmt::Display for PresencePenalty {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.0)
@@ -309,6 +401,103 @@ pub enum StopType {
 pub struct StreamOptions {
     pub include_usage: bool,
 }
+/// A function the model may call
+///
+/// # Example
+/// ```
+/// use clia_deepseek_rs::types::request::{RequestBody, Tool, ToolChoice};
+///
+/// let weather = Tool::function(
+///     "get_weather",
+///     "Returns the weather in a city",
+///     serde_json::json!({
+///         "type": "object",
+///         "properties": { "city": { "type": "string" } },
+///         "required": ["city"]
+///     }),
+/// );
+/// let request = RequestBody::default()
+///     .with_tools(vec![weather])
+///     .with_tool_choice(ToolChoice::function("get_weather"));
+/// assert_eq!(request.tools()[0].function.name, "get_weather");
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Tool {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub function: FunctionDefinition,
+}
+
+impl Tool {
+    /// Creates a function tool whose arguments follow the JSON schema `parameters`
+    pub fn function(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: serde_json::Value,
+    ) -> Self {
+        Tool {
+            type_: "function".to_string(),
+            function: FunctionDefinition {
+                name: name.into(),
+                description: Some(description.into()),
+                parameters,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FunctionDefinition {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// JSON schema of the arguments
+    pub parameters: serde_json::Value,
+}
+
+/// Whether and which tool the model must call
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ToolChoice {
+    Mode(ToolChoiceMode),
+    Function(NamedToolChoice),
+}
+
+impl ToolChoice {
+    /// Forces a call to the function `name`
+    pub fn function(name: impl Into<String>) -> Self {
+        ToolChoice::Function(NamedToolChoice {
+            type_: "function".to_string(),
+            function: NamedFunction { name: name.into() },
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ToolChoiceMode {
+    /// Never call a tool
+    #[serde(rename = "none")]
+    None,
+    /// Let the model decide
+    #[serde(rename = "auto")]
+    Auto,
+    /// Call at least one tool
+    #[serde(rename = "required")]
+    Required,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct NamedToolChoice {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub function: NamedFunction,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct NamedFunction {
+    pub name: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 
 pub struct Temperature(f32);
@@ -381,13 +570,19 @@ pub enum Role {
     System,
     #[serde(rename = "assistant")]
     Assistant,
+    #[serde(rename = "tool")]
+    Tool,
 }
 
 /// A chat message with role and content
 ///
+/// Besides plain system, user and assistant messages this covers assistant messages
+/// carrying tool calls, tool results answering them, and assistant prefixes for
+/// prefix completion.
+///
 /// # Examples
 /// ```
-/// use deepseek_rs::client::chat_completions::request::{Message, Role};
+/// use clia_deepseek_rs::types::request::{Message, Role};
 ///
 /// let user_msg = Message::new_user_message("Hello".to_string());
 /// assert!(matches!(user_msg.role, Role::User));
@@ -398,12 +593,27 @@ pub enum Role {
 /// );
 /// assert!(matches!(system_msg.role, Role::System));
 /// assert_eq!(system_msg.name, Some("sys".to_string()));
+///
+/// let tool_msg = Message::new_tool_message("22°C".to_string(), "call_0".to_string());
+/// assert!(matches!(tool_msg.role, Role::Tool));
 /// ```
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct Message {
     pub role: Role,
     pub content: String,
     pub name: Option<String>,
+    /// Reasoning of an assistant message; only sent back for prefix completion
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
+    /// Tool calls requested by an assistant message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolsCall>>,
+    /// The tool call a tool message answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// Marks the last assistant message as a prefix the model should continue
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<bool>,
 }
 impl Message {
     /// Creates a new message with specified role, content and optional name
@@ -412,67 +622,120 @@ impl Message {
             role,
             content,
             name,
+            reasoning_content: None,
+            tool_calls: None,
+            tool_call_id: None,
+            prefix: None,
         }
     }
 
     /// Creates a new user message
     pub fn new_user_message(content: String) -> Self {
-        Message {
-            role: Role::User,
-            content,
-            name: None,
-        }
+        Message::new(Role::User, content, None)
     }
 
     /// Creates a new system message
     pub fn new_system_message(content: String) -> Self {
-        Message {
-            role: Role::System,
-            content,
-            name: None,
-        }
+        Message::new(Role::System, content, None)
     }
 
     /// Creates a new assistant message
     pub fn new_assistant_message(content: String) -> Self {
-        Message {
-            role: Role::Assistant,
-            content,
-            name: None,
-        }
+        Message::new(Role::Assistant, content, None)
     }
 
     /// Creates a new user message with a name
     pub fn new_user_message_with_name(content: String, name: String) -> Self {
-        Message {
-            role: Role::User,
-            content,
-            name: Some(name),
-        }
+        Message::new(Role::User, content, Some(name))
     }
 
     /// Creates a new system message with a name
     pub fn new_system_message_with_name(content: String, name: String) -> Self {
-        Message {
-            role: Role::System,
-            content,
-            name: Some(name),
-        }
+        Message::new(Role::System, content, Some(name))
     }
 
     /// Creates a new assistant message with a name
     pub fn new_assistant_message_with_name(content: String, name: String) -> Self {
+        Message::new(Role::Assistant, content, Some(name))
+    }
+
+    /// Creates a tool message with the result of the tool call `tool_call_id`
+    pub fn new_tool_message(content: String, tool_call_id: String) -> Self {
         Message {
-            role: Role::Assistant,
-            content,
-            name: Some(name),
+            tool_call_id: Some(tool_call_id),
+            ..Message::new(Role::Tool, content, None)
+        }
+    }
+
+    /// Creates an assistant message the model continues from (prefix completion)
+    pub fn new_assistant_prefix_message(content: String) -> Self {
+        Message {
+            prefix: Some(true),
+            ..Message::new(Role::Assistant, content, None)
         }
     }
 }
 
+/// Converts a reply into a message for the next request
+///
+/// Missing content becomes an empty string. Use [`RequestBody::push_response`] to
+/// continue a conversation, as it also drops the reasoning the API rejects as input.
+impl From<ResponseMessage> for Message {
+    fn from(message: ResponseMessage) -> Self {
+        Message {
+            reasoning_content: message.reasoning_content,
+            tool_calls: message.tool_calls,
+            ..Message::new(message.role, message.content.unwrap_or_default(), None)
+        }
+    }
+}
+
+/// Converts an assistant message back into a reply
+///
+/// Empty content becomes `None` only next to tool calls, which is how the API sends
+/// them; otherwise it stays an empty string.
+impl TryFrom<Message> for ResponseMessage {
+    type Error = MessageErrors;
+
+    fn try_from(message: Message) -> Result<Self, Self::Error> {
+        if message.role != Role::Assistant {
+            return Err(MessageErrors::NotAnAssistantMessage(message.role));
+        }
+        Ok(ResponseMessage {
+            content: Some(message.content)
+                .filter(|c| !c.is_empty() || message.tool_calls.is_none()),
+            reasoning_content: message.reasoning_content,
+            role: message.role,
+            tool_calls: message.tool_calls,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::response_with;
+
+    #[test]
+    fn test_tools_serialization() {
+        let request = RequestBody::default();
+        let json = serde_json::to_value(&request).unwrap();
+        assert!(json.get("tools").is_none());
+
+        let request = request
+            .with_tools(vec![Tool::function("f", "does f", serde_json::json!({}))])
+            .with_tool_choice(ToolChoice::Mode(ToolChoiceMode::Auto));
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["tools"][0]["type"], "function");
+        assert_eq!(json["tools"][0]["function"]["name"], "f");
+        assert_eq!(json["tool_choice"], "auto");
+        let json = serde_json::to_value(ToolChoice::function("f")).unwrap();
+        assert_eq!(json["function"]["name"], "f");
+        assert_eq!(
+            serde_json::from_value::<RequestBody>(serde_json::to_value(&request).unwrap()).unwrap(),
+            request
+        );
+    }
 
     #[test]
     fn test_frequency_penalty() {
@@ -504,18 +767,18 @@ mod tests {
         let text_format = ResponseFormat::new(ResponseFormatType::Text);
         let default_format = ResponseFormat::default();
 
-        match default_format.type_ {
-            ResponseFormatType::Json => assert!(true),
-            _ => assert!(false, "Default should be Json"),
-        }
-        match json_format.type_ {
-            ResponseFormatType::Json => assert!(true),
-            _ => assert!(false, "Expected Json"),
-        }
-        match text_format.type_ {
-            ResponseFormatType::Text => assert!(true),
-            _ => assert!(false, "Expected Text"),
-        }
+        assert!(
+            matches!(default_format.type_, ResponseFormatType::Json),
+            "Default should be Json"
+        );
+        assert!(
+            matches!(json_format.type_, ResponseFormatType::Json),
+            "Expected Json"
+        );
+        assert!(
+            matches!(text_format.type_, ResponseFormatType::Text),
+            "Expected Text"
+        );
     }
 
     #[test]
@@ -559,6 +822,76 @@ mod tests {
         assert!(assistant_msg.name.is_none());
     }
 
+    #[test]
+    fn test_message_serialization() {
+        let json = serde_json::to_value(Message::new_user_message("Hi".to_string())).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "role": "user", "content": "Hi", "name": null })
+        );
+        let json = serde_json::to_value(Message::new_tool_message(
+            "42".to_string(),
+            "call_0".to_string(),
+        ))
+        .unwrap();
+        assert_eq!(json["role"], "tool");
+        assert_eq!(json["tool_call_id"], "call_0");
+        let json =
+            serde_json::to_value(Message::new_assistant_prefix_message("```".to_string())).unwrap();
+        assert_eq!(json["prefix"], true);
+    }
+
+    #[test]
+    fn test_message_conversion() {
+        let reply = ResponseMessage {
+            content: None,
+            reasoning_content: Some("think".to_string()),
+            role: Role::Assistant,
+            tool_calls: Some(vec![ToolsCall {
+                id: "call_0".to_string(),
+                type_: "function".to_string(),
+                function_call: super::super::response::FunctionCall {
+                    name: "weather".to_string(),
+                    arguments: "{}".to_string(),
+                },
+            }]),
+        };
+        let message = Message::from(reply.clone());
+        assert!(matches!(message.role, Role::Assistant));
+        assert_eq!(message.content, "");
+        assert_eq!(message.tool_calls.as_ref().unwrap().len(), 1);
+        assert_eq!(ResponseMessage::try_from(message).unwrap(), reply);
+
+        let empty = ResponseMessage {
+            content: Some(String::new()),
+            reasoning_content: None,
+            role: Role::Assistant,
+            tool_calls: None,
+        };
+        let message = Message::from(empty.clone());
+        assert_eq!(ResponseMessage::try_from(message).unwrap(), empty);
+
+        let user = Message::new_user_message("Hi".to_string());
+        assert!(matches!(
+            ResponseMessage::try_from(user),
+            Err(MessageErrors::NotAnAssistantMessage(Role::User))
+        ));
+    }
+
+    #[test]
+    fn test_push_response() {
+        let response = response_with(
+            serde_json::json!({ "role": "assistant", "content": "Paris", "reasoning_content": "..." }),
+            "stop",
+        );
+        let mut request =
+            RequestBody::new_messages(vec![Message::new_user_message("Capital?".to_string())]);
+        request.push_response(&response);
+        assert_eq!(request.messages.len(), 2);
+        assert_eq!(request.messages[1].content, "Paris");
+        assert!(request.messages[1].reasoning_content.is_none());
+    }
+
     #[test]
     fn test_stop_type() {
         let single_stop = StopType::Stop("stop".to_string());
@@ -625,6 +958,14 @@ mod tests {
         assert_eq!(req.top_logprobs.unwrap().0, 5);
     }
 
+    #[test]
+    fn test_model_name() {
+        for model in [Model::DeepseekChat, Model::DeepSeekReasoner] {
+            let json = serde_json::to_value(&model).unwrap();
+            assert_eq!(json, model.to_string());
+        }
+    }
+
     #[test]
     fn test_request_body_default() {
         let req = RequestBody::default();
@@ -641,5 +982,6 @@ mod tests {
         assert!(req.top_p.is_none());
         assert!(req.logprobs.is_none());
         assert!(req.top_logprobs.is_none());
+        assert!(!req.cache_bypass);
     }
 }
@@ -0,0 +1,216 @@
+use std::ops::AddAssign;
+
+use serde::{Deserialize, Serialize};
+
+use super::request::Role;
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+
+pub struct ChatCompletionsResponse {
+    pub id: String,
+    pub choices: Vec<ChatCompletionsChoices>,
+    pub created: i32, // Unix timestamp in seconds
+    pub model: String,
+    pub object: String, //TODO: add enum
+    pub usage: Usage,
+    /// Set when the response was served from a response cache instead of the API
+    #[serde(skip)]
+    pub cache_hit: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum FinishReasons {
+    // Possible values: [stop, length, content_filter, tool_calls, insufficient_system_resource]
+    #[serde(rename = "stop")]
+    Stop,
+    #[serde(rename = "length")]
+    Length,
+    #[serde(rename = "content_filter")]
+    ContentFilter,
+    #[serde(rename = "tool_calls")]
+    ToolCalls,
+    #[serde(rename = "insufficient_system_resource")]
+    InsufficientSystemResource,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ChatCompletionsChoices {
+    pub finish_reason: FinishReasons,
+    pub index: i32,
+    pub message: Message,
+    //TODO: add log_prob
+}
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub content: Option<String>,
+    pub reasoning_content: Option<String>,
+    pub role: Role,
+    pub tool_calls: Option<Vec<ToolsCall>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ToolsCall {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(rename = "function")]
+    pub function_call: FunctionCall,
+}
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct FunctionCall {
+    pub name: String,
+    pub arguments: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct Usage {
+    pub completion_tokens: i32,
+    pub prompt_tokens: i32,
+    /// Only reported by DeepSeek; zero for other OpenAI-compatible servers
+    #[serde(default)]
+    pub prompt_cache_hit_tokens: i32,
+    #[serde(default)]
+    pub prompt_cache_miss_tokens: i32,
+    pub total_tokens: i32,
+    pub completion_tokens_details: Option<CompletionTokensDetails>,
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+}
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+
+pub struct CompletionTokensDetails {
+    pub reasoning_tokens: i32,
+}
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+
+pub struct PromptTokensDetails {
+    pub cached_tokens: i32,
+}
+
+/// Adds up the usage of several calls, e.g. to total the cost of a conversation
+impl AddAssign<&Usage> for Usage {
+    fn add_assign(&mut self, other: &Usage) {
+        self.completion_tokens += other.completion_tokens;
+        self.prompt_tokens += other.prompt_tokens;
+        self.prompt_cache_hit_tokens += other.prompt_cache_hit_tokens;
+        self.prompt_cache_miss_tokens += other.prompt_cache_miss_tokens;
+        self.total_tokens += other.total_tokens;
+        self.completion_tokens_details = match (
+            self.completion_tokens_details.take(),
+            &other.completion_tokens_details,
+        ) {
+            (Some(a), Some(b)) => Some(CompletionTokensDetails {
+                reasoning_tokens: a.reasoning_tokens + b.reasoning_tokens,
+            }),
+            (a, b) => a.or_else(|| b.clone()),
+        };
+        self.prompt_tokens_details = match (
+            self.prompt_tokens_details.take(),
+            &other.prompt_tokens_details,
+        ) {
+            (Some(a), Some(b)) => Some(PromptTokensDetails {
+                cached_tokens: a.cached_tokens + b.cached_tokens,
+            }),
+            (a, b) => a.or_else(|| b.clone()),
+        };
+    }
+}
+
+/// A single server-sent chunk of a streamed chat completion
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ChatCompletionsChunk {
+    pub id: String,
+    pub choices: Vec<ChunkChoice>,
+    pub created: i32, // Unix timestamp in seconds
+    pub model: String,
+    pub object: String,
+    pub usage: Option<Usage>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ChunkChoice {
+    pub delta: Delta,
+    pub finish_reason: Option<FinishReasons>,
+    pub index: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct Delta {
+    pub content: Option<String>,
+    pub reasoning_content: Option<String>,
+    pub role: Option<Role>,
+    pub tool_calls: Option<Vec<ToolCallDelta>>,
+}
+
+/// A fragment of a tool call; fragments sharing an `index` belong to the same call
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ToolCallDelta {
+    pub index: i32,
+    pub id: Option<String>,
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    #[serde(rename = "function")]
+    pub function_call: Option<FunctionCallDelta>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct FunctionCallDelta {
+    pub name: Option<String>,
+    pub arguments: Option<String>,
+}
+
+#[cfg(feature = "chrono")]
+impl ChatCompletionsResponse {
+    /// Returns `created` as a timestamp
+    pub fn created_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        chrono::DateTime::from_timestamp(self.created.into(), 0)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl ChatCompletionsChunk {
+    /// Returns `created` as a timestamp
+    pub fn created_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        chrono::DateTime::from_timestamp(self.created.into(), 0)
+    }
+}
+
+/// Turns a full response into a single chunk, e.g. to replay it as a stream
+impl From<ChatCompletionsResponse> for ChatCompletionsChunk {
+    fn from(response: ChatCompletionsResponse) -> Self {
+        let choices = response
+            .choices
+            .into_iter()
+            .map(|choice| ChunkChoice {
+                delta: Delta {
+                    content: choice.message.content,
+                    reasoning_content: choice.message.reasoning_content,
+                    role: Some(choice.message.role),
+                    tool_calls: choice.message.tool_calls.map(|calls| {
+                        calls
+                            .into_iter()
+                            .zip(0..)
+                            .map(|(call, index)| ToolCallDelta {
+                                index,
+                                id: Some(call.id),
+                                type_: Some(call.type_),
+                                function_call: Some(FunctionCallDelta {
+                                    name: Some(call.function_call.name),
+                                    arguments: Some(call.function_call.arguments),
+                                }),
+                            })
+                            .collect()
+                    }),
+                },
+                finish_reason: Some(choice.finish_reason),
+                index: choice.index,
+            })
+            .collect();
+        ChatCompletionsChunk {
+            id: response.id,
+            choices,
+            created: response.created,
+            model: response.model,
+            object: "chat.completion.chunk".to_string(),
+            usage: Some(response.usage),
+        }
+    }
+}